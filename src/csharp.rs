@@ -1,5 +1,6 @@
 mod debug_adapters;
 mod language_servers;
+mod tools;
 mod utils;
 
 use language_servers::Roslyn;
@@ -16,6 +17,8 @@ impl CsharpExtension {}
 
 impl zed::Extension for CsharpExtension {
     fn new() -> Self {
+        utils::install::cleanup_partial_installs();
+
         Self {
             roslyn: None,
             netcoredbg: None,
@@ -42,10 +45,10 @@ impl zed::Extension for CsharpExtension {
         language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
     ) -> Result<Option<zed::serde_json::Value>> {
-        if language_server_id.as_ref() == Roslyn::LANGUAGE_SERVER_ID {
-            if let Some(roslyn) = self.roslyn.as_mut() {
-                return roslyn.configuration_options(worktree);
-            }
+        if language_server_id.as_ref() == Roslyn::LANGUAGE_SERVER_ID
+            && let Some(roslyn) = self.roslyn.as_mut()
+        {
+            return roslyn.configuration_options(worktree);
         }
         Ok(None)
     }
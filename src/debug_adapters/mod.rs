@@ -1,3 +1,3 @@
 mod netcoredbg;
 
-pub use netcoredbg::*;
\ No newline at end of file
+pub use netcoredbg::*;
@@ -1,20 +1,23 @@
 use serde_json::Value;
-use std::
-    fs::{self}
-;
-use zed_extension_api::{
-    self as zed, Result,
-    settings::LspSettings,
-};
+use zed_extension_api::{self as zed, Result, settings::LspSettings};
 
+use crate::tools::{GitHubRelease, ManagedTool, PackageSource, ToolManager, UpgradePolicy, dotnet};
 use crate::utils;
 
 const NETCOREDBG_REPO: &str = "marcptrs/netcoredbg";
 const NETCOREDBG_TAG: &str = "v3.1.2-1054";
 const NETCOREDBG: &str = "netcoredbg";
+/// The runtime identifiers that netcoredbg releases are published for.
+const NETCOREDBG_RUNTIME_IDENTIFIERS: &[&str] = &[
+    "linux-x64",
+    "linux-arm64",
+    "osx-x64",
+    "osx-arm64",
+    "win-x64",
+];
 
 pub struct NetcoreDbg {
-    cached_netcoredbg_path: Option<String>,
+    tools: ToolManager,
 }
 
 impl NetcoreDbg {
@@ -22,7 +25,7 @@ impl NetcoreDbg {
 
     pub fn new() -> Self {
         NetcoreDbg {
-            cached_netcoredbg_path: None,
+            tools: ToolManager::default(),
         }
     }
 
@@ -109,69 +112,74 @@ impl NetcoreDbg {
     }
 
     fn ensure_netcoredbg(
-        self: &mut Self,
+        &mut self,
         worktree: &zed::Worktree,
     ) -> std::result::Result<zed::Command, String> {
         let default_args = vec!["--interpreter=vscode".to_string()];
 
-        let settings = LspSettings::for_worktree(Self::DEBUG_ADAPTER_ID, worktree).ok();
-        let binary_settings = settings.and_then(|lsp_settings| lsp_settings.binary);
-        let binary_args = binary_settings
-            .as_ref()
-            .and_then(|binary_settings| binary_settings.arguments.clone());
-
-        if let Some(path) = binary_settings
-            .and_then(|binary_settings| binary_settings.path)
-            .or_else(|| {
-                self.cached_netcoredbg_path
-                    .as_ref()
-                    .filter(|path| fs::metadata(path).map_or(false, |stat| stat.is_file()))
-                    .cloned()
-            })
-        {
-            return Ok(zed::Command {
-                command: path,
-                args: binary_args.unwrap_or(default_args),
-                env: Default::default(),
-            });
-        }
+        let binary_args = LspSettings::for_worktree(Self::DEBUG_ADAPTER_ID, worktree)
+            .ok()
+            .and_then(|lsp_settings| lsp_settings.binary)
+            .and_then(|binary_settings| binary_settings.arguments);
+
+        let netcoredbg_path = self.tools.resolve(&NetcoreDbgTool, worktree, None)?;
+        let dotnet_root = dotnet::resolve_sdk(
+            &mut self.tools,
+            Self::DEBUG_ADAPTER_ID,
+            worktree,
+            &worktree.root_path(),
+            None,
+        )?;
+
+        let installed_sdks = match dotnet_root {
+            Some(_) => None,
+            None => utils::list_dotnet_sdks(worktree),
+        };
+
+        Ok(zed::Command {
+            command: netcoredbg_path,
+            args: binary_args.unwrap_or(default_args),
+            env: utils::get_command_env(
+                Self::DEBUG_ADAPTER_ID,
+                worktree,
+                dotnet_root.as_deref(),
+                installed_sdks.as_deref(),
+            ),
+        })
+    }
+}
 
-        let executable = utils::get_executable(NETCOREDBG);
+/// netcoredbg, published as an archive per RID in GitHub releases.
+struct NetcoreDbgTool;
 
-        if let Some(path) = worktree.which(executable.as_str()) {
-            return Ok(zed::Command {
-                command: path,
-                args: binary_args.unwrap_or(default_args),
-                env: Default::default(),
-            });
-        }
+impl ManagedTool for NetcoreDbgTool {
+    fn id(&self) -> &'static str {
+        NETCOREDBG
+    }
 
-        if let Some(path) = &self.cached_netcoredbg_path
-            && fs::metadata(path).map_or(false, |stat| stat.is_file())
-        {
-            return Ok(zed::Command {
-                command: path.into(),
-                args: binary_args.unwrap_or(default_args),
-                env: Default::default(),
-            });
-        }
+    fn settings_id(&self) -> &'static str {
+        NetcoreDbg::DEBUG_ADAPTER_ID
+    }
 
-        let netcoredbg_path = utils::ensure_github_release(
-            NETCOREDBG,
-            NETCOREDBG_REPO,
-            NETCOREDBG_TAG,
-            || Self::get_netcoredbg_package_id(),
-            Self::get_github_asset_file_type())?;
+    fn package_source(&self, _worktree: &zed::Worktree) -> PackageSource {
+        let (os, _) = zed::current_platform();
 
-        return Ok(zed::Command {
-            command: netcoredbg_path,
-            args: binary_args.unwrap_or(default_args),
-            env: Default::default(),
-        });
+        PackageSource::GitHubRelease(GitHubRelease {
+            repo: NETCOREDBG_REPO,
+            tag: NETCOREDBG_TAG,
+            file_type: match os {
+                zed::Os::Windows => zed::DownloadedFileType::Zip,
+                _ => zed::DownloadedFileType::GzipTar,
+            },
+            compatible_versions: None,
+        })
     }
 
-    fn get_netcoredbg_package_id() -> String {
-        let runtime_identifier = utils::get_runtime_identifier();
+    fn published_runtime_identifiers(&self) -> &'static [&'static str] {
+        NETCOREDBG_RUNTIME_IDENTIFIERS
+    }
+
+    fn package_id(&self, runtime_identifier: &str) -> String {
         let (os, _) = zed::current_platform();
 
         let ext = match os {
@@ -182,11 +190,13 @@ impl NetcoreDbg {
         format!("{NETCOREDBG}-{runtime_identifier}.{ext}")
     }
 
-    fn get_github_asset_file_type() -> zed::DownloadedFileType {
-        let (os, _) = zed::current_platform();
-        match os {
-            zed::Os::Windows => zed_extension_api::DownloadedFileType::Zip,
-            _ => zed::DownloadedFileType::GzipTar,
-        }
+    fn executable_name(&self) -> String {
+        utils::get_executable(NETCOREDBG)
+    }
+
+    /// netcoredbg releases have no compatibility range, so a newer release is not
+    /// installed over a working one unless asked for.
+    fn default_upgrade_policy(&self) -> UpgradePolicy {
+        UpgradePolicy::Never
     }
 }
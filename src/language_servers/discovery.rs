@@ -0,0 +1,95 @@
+use std::path::{Path, PathBuf};
+
+use zed_extension_api::{self as zed};
+
+use crate::utils;
+
+const DOTNET_TOOL: &str = "roslyn-language-server";
+
+/// Oldest server version whose command line (`--pipe`, `--extensionLogDirectory`) the
+/// roslynls wrapper relies on.
+const MIN_LANGUAGE_SERVER_VERSION: semver::Version = semver::Version::new(4, 12, 0);
+
+#[derive(Debug, Clone)]
+pub struct DiscoveredInstall {
+    pub source: &'static str,
+    pub version: semver::Version,
+    pub path: String,
+}
+
+/// Looks for a language server installed by another tool, so it can be reused instead of
+/// downloading another copy.
+///
+/// The home directory cannot be scanned (see [file access](crate::utils#file-access)), so
+/// installs are found by asking `dotnet`. Only the apphost is found:
+/// the `.dll` of a global tool sits in a folder named after its target framework, which
+/// cannot be listed.
+pub fn discover_language_server(
+    worktree: &zed::Worktree,
+    file_name: &str,
+) -> Option<DiscoveredInstall> {
+    if file_name.ends_with(".dll") {
+        return None;
+    }
+
+    let install = find_dotnet_tool_install(worktree)?;
+
+    println!(
+        "[zed-roslynls] Found Roslyn Language Server {} from {} at {}",
+        install.version, install.source, install.path
+    );
+
+    Some(install).filter(|install| install.version >= MIN_LANGUAGE_SERVER_VERSION)
+}
+
+/// `dotnet tool install --global roslyn-language-server` puts a launcher in
+/// `~/.dotnet/tools`, which is usually on `PATH`.
+fn find_dotnet_tool_install(worktree: &zed::Worktree) -> Option<DiscoveredInstall> {
+    let version = get_global_tool_version(worktree, DOTNET_TOOL)?;
+
+    let path = worktree.which(DOTNET_TOOL).or_else(|| {
+        let launcher = get_home_dir(worktree)?
+            .join(".dotnet")
+            .join("tools")
+            .join(utils::get_executable(DOTNET_TOOL));
+        Some(launcher.to_string_lossy().to_string())
+    })?;
+
+    Some(DiscoveredInstall {
+        source: "the roslyn-language-server dotnet tool",
+        version,
+        path,
+    })
+}
+
+/// Reads the version of a global tool from `dotnet tool list --global`, which prints a
+/// header, a separator and then `<package id> <version> <commands>` per tool.
+fn get_global_tool_version(worktree: &zed::Worktree, package_id: &str) -> Option<semver::Version> {
+    let output = zed::process::Command::new("dotnet")
+        .args(["tool", "list", "--global"])
+        .envs(worktree.shell_env())
+        .output()
+        .ok()
+        .filter(|output| output.status == Some(0))?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let mut columns = line.split_whitespace();
+            columns
+                .next()
+                .filter(|id| id.eq_ignore_ascii_case(package_id))?;
+            columns.next().map(String::from)
+        })
+        .and_then(|version| semver::Version::parse(&version).ok())
+}
+
+fn get_home_dir(worktree: &zed::Worktree) -> Option<PathBuf> {
+    let env = worktree.shell_env();
+
+    ["HOME", "USERPROFILE"].iter().find_map(|name| {
+        env.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| Path::new(value).to_path_buf())
+    })
+}
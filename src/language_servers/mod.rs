@@ -1,4 +1,6 @@
+mod discovery;
 pub mod roslyn;
-mod model;
+mod settings;
+mod solution;
 
 pub use roslyn::*;
@@ -1,9 +1,13 @@
-use std::fs::{self};
+use std::path::Path;
+
+use serde::Deserialize;
 use zed_extension_api::{
     self as zed, LanguageServerId, Result, serde_json::Map, settings::LspSettings,
 };
 
-use crate::language_servers::model::NuGetPackagesResponse;
+use crate::language_servers::solution::{self, Workspace};
+use crate::language_servers::{discovery, settings};
+use crate::tools::{GitHubRelease, ManagedTool, NuGetFeed, PackageSource, ToolManager, dotnet};
 use crate::utils;
 
 const ORGANIZATION: &str = "azure-public";
@@ -11,16 +15,87 @@ const PROJECT: &str = "vside";
 const FEED: &str = "vs-impl";
 const ROSLYNLS: &str = "roslynls";
 const ROSLYNLS_PATH_KEY: &str = "roslynls_path";
+const ROSLYNLS_VERSION_KEY: &str = "roslynls_version";
+const LANGUAGE_SERVER_PATH_KEY: &str = "roslyn_path";
+const LANGUAGE_SERVER_VERSION_KEY: &str = "roslyn_version";
+const LANGUAGE_SERVER_UPGRADE_POLICY_KEY: &str = "roslyn_upgrade_policy";
+const ROSLYNLS_UPGRADE_POLICY_KEY: &str = "roslynls_upgrade_policy";
+const LOCAL_FEED_KEY: &str = "language_server_local_feed";
+const DISCOVER_INSTALLS_KEY: &str = "discover_existing_installs";
+const HOST_MODE_KEY: &str = "host_mode";
+const SOLUTION_KEY: &str = "solution";
+const PROJECT_ROOT_KEY: &str = "project_root";
+const PRESET_KEY: &str = "preset";
+const DOTNET_SDK_PATH_KEY: &str = "dotnet_sdk_path";
+const DOTNET_SDK_VERSION_KEY: &str = "dotnet_sdk_version";
+const DOTNET_SDK_UPGRADE_POLICY_KEY: &str = "dotnet_sdk_upgrade_policy";
+const EXTRA_ARGUMENTS_KEY: &str = "extra_arguments";
+const INCLUDE_PROJECTS_KEY: &str = "include_projects";
+const EXCLUDE_PROJECTS_KEY: &str = "exclude_projects";
+const EXTENSION_SETTING_KEYS: &[&str] = &[
+    ROSLYNLS_PATH_KEY,
+    ROSLYNLS_VERSION_KEY,
+    LANGUAGE_SERVER_PATH_KEY,
+    LANGUAGE_SERVER_VERSION_KEY,
+    LANGUAGE_SERVER_UPGRADE_POLICY_KEY,
+    ROSLYNLS_UPGRADE_POLICY_KEY,
+    LOCAL_FEED_KEY,
+    DISCOVER_INSTALLS_KEY,
+    HOST_MODE_KEY,
+    SOLUTION_KEY,
+    PROJECT_ROOT_KEY,
+    PRESET_KEY,
+    DOTNET_SDK_PATH_KEY,
+    DOTNET_SDK_VERSION_KEY,
+    DOTNET_SDK_UPGRADE_POLICY_KEY,
+    dotnet::INSTALL_DOTNET_SDK_KEY,
+    dotnet::DOTNET_SDK_FEED_KEY,
+    EXTRA_ARGUMENTS_KEY,
+    INCLUDE_PROJECTS_KEY,
+    EXCLUDE_PROJECTS_KEY,
+    utils::KEEP_PREVIOUS_VERSIONS_KEY,
+    utils::RUNTIME_IDENTIFIER_KEY,
+    utils::ALLOW_PATH_FALLBACK_KEY,
+    utils::GITHUB_RELEASE_URL_KEY,
+];
 const ROSLYNLS_REPO: &str = "fallenwood/zed-roslynls";
 const ROSLYNLS_TAG: &str = "v0.0.2";
+/// The roslynls releases whose command line `Roslyn::cmd` knows how to build.
+const ROSLYNLS_COMPATIBLE_VERSIONS: &str = ">=0.0.2, <0.1.0";
+/// The roslynls release that introduced each argument.
+const ROSLYNLS_ARGUMENTS: &[(&str, semver::Version)] = &[
+    ("--lsp", semver::Version::new(0, 0, 1)),
+    ("--project-root", semver::Version::new(0, 0, 1)),
+    ("--log-file-path", semver::Version::new(0, 0, 1)),
+    ("--wrapper-rpc-type", semver::Version::new(0, 0, 1)),
+    ("--lsp-rpc-type", semver::Version::new(0, 0, 1)),
+    ("--lsp-dll", semver::Version::new(0, 0, 3)),
+    ("--solution", semver::Version::new(0, 0, 3)),
+    ("--projects-file", semver::Version::new(0, 0, 3)),
+    ("--discover-project-root", semver::Version::new(0, 0, 3)),
+];
 const LANGUAGE_SERVER: &str = "Microsoft.CodeAnalysis.LanguageServer";
+const NEUTRAL_RUNTIME_IDENTIFIER: &str = "neutral";
+/// The runtime identifiers of the RID-specific server packages in the feed.
+const LANGUAGE_SERVER_RUNTIME_IDENTIFIERS: &[&str] = &[
+    "linux-x64",
+    "linux-arm64",
+    "linux-musl-x64",
+    "linux-musl-arm64",
+    "osx-x64",
+    "osx-arm64",
+    "win-x64",
+    "win-arm64",
+];
+/// The runtime identifiers that roslynls releases are published for. The Linux builds are
+/// statically linked against musl, so they also run on glibc systems.
+const ROSLYNLS_RUNTIME_IDENTIFIERS: &[&str] = &["linux-x64", "linux-arm64", "win-x64", "win-arm64"];
 
 // Example version
 // const PACKAGE_VERSION: &str = "5.1.0-1.25476.5";
 
 pub struct Roslyn {
-    cached_language_server_path: Option<String>,
-    cached_roslynls_path: Option<String>,
+    tools: ToolManager,
 }
 
 impl Roslyn {
@@ -28,91 +103,310 @@ impl Roslyn {
 
     pub fn new() -> Self {
         Roslyn {
-            cached_language_server_path: None,
-            cached_roslynls_path: None,
+            tools: ToolManager::default(),
         }
     }
 
     pub fn language_server_cmd(
         &mut self,
-        _language_server_id: &LanguageServerId,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::Command> {
+        self.resolve_language_server_cmd(language_server_id, worktree)
+            .inspect_err(|e| {
+                zed::set_language_server_installation_status(
+                    language_server_id,
+                    &zed::LanguageServerInstallationStatus::Failed(e.clone()),
+                )
+            })
+    }
+
+    fn resolve_language_server_cmd(
+        &mut self,
+        language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
     ) -> Result<zed::Command> {
         let settings = LspSettings::for_worktree(Self::LANGUAGE_SERVER_ID, worktree).ok();
 
-        let roslynls_path = self.ensure_roslynls(worktree)?;
+        let roslynls_path =
+            self.tools
+                .resolve(&RoslynlsTool, worktree, Some(language_server_id))?;
+
+        let binary_args = settings
+            .and_then(|lsp_settings| lsp_settings.binary)
+            .and_then(|binary_settings| binary_settings.arguments);
+        let extra_arguments = utils::get_setting::<Vec<String>>(
+            Self::LANGUAGE_SERVER_ID,
+            worktree,
+            EXTRA_ARGUMENTS_KEY,
+        )
+        .unwrap_or_default();
 
-        self.cached_roslynls_path = Some(roslynls_path.clone());
+        let host_mode =
+            utils::get_setting::<HostMode>(Self::LANGUAGE_SERVER_ID, worktree, HOST_MODE_KEY)
+                .unwrap_or_default();
 
-        let binary_settings = settings.and_then(|lsp_settings| lsp_settings.binary);
-        let binary_args = binary_settings
-            .as_ref()
-            .and_then(|binary_settings| binary_settings.arguments.clone());
+        let project_root = Self::get_project_root(worktree);
+        let sdk_root = project_root.clone().unwrap_or_else(|| worktree.root_path());
+
+        let workspace = match Self::get_workspace(worktree, project_root.as_deref())? {
+            // Without a configured root, the worktree may be a subfolder of a repository
+            // or hold several of them, so the wrapper looks around it for the project root.
+            None if project_root.is_none() => Some(Workspace::Discover),
+            workspace => workspace,
+        };
+        let workspace = Self::filter_discovered_workspace(worktree, &roslynls_path, workspace);
+
+        // Which arguments are passed only depends on the settings and the workspace, so a
+        // wrapper that is too old is reported before the SDK or the server is downloaded.
+        Self::check_roslynls_arguments(&Self::cmd(
+            roslynls_path.clone(),
+            (host_mode == HostMode::Dotnet).then(String::new),
+            String::new(),
+            sdk_root.clone(),
+            workspace.clone(),
+            binary_args.clone(),
+            extra_arguments.clone(),
+        )?)?;
+
+        let dotnet_root = dotnet::resolve_sdk(
+            &mut self.tools,
+            Self::LANGUAGE_SERVER_ID,
+            worktree,
+            &sdk_root,
+            Some(language_server_id),
+        )?;
+
+        // A managed SDK is installed at the version global.json asks for, unless the
+        // user chose another one with dotnet_sdk_version or dotnet_sdk_path. Otherwise the
+        // SDKs on PATH are checked and also tell where DOTNET_ROOT is.
+        let installed_sdks = match dotnet_root {
+            Some(_) => None,
+            None => {
+                let installed_sdks = utils::list_dotnet_sdks(worktree);
+                dotnet::check_sdk(worktree, &sdk_root, installed_sdks.as_deref())?;
+                installed_sdks
+            }
+        };
 
-        if let Some(path) = binary_settings
-            .and_then(|binary_settings| binary_settings.path)
-            .or_else(|| {
-                self.cached_language_server_path
+        let dotnet_path = match host_mode {
+            HostMode::AppHost => None,
+            HostMode::Dotnet => Some(
+                dotnet_root
                     .as_ref()
-                    .filter(|path| fs::metadata(path).map_or(false, |stat| stat.is_file()))
-                    .cloned()
-            })
+                    .map(|root| {
+                        Path::new(root)
+                            .join(utils::get_executable("dotnet"))
+                            .to_string_lossy()
+                            .to_string()
+                    })
+                    .or_else(|| utils::find_dotnet(worktree))
+                    .ok_or(
+                        "host_mode is \"dotnet\" but no dotnet host was found in DOTNET_ROOT or on PATH",
+                    )?,
+            ),
+        };
+
+        let language_server_path = self.tools.resolve(
+            &LanguageServerTool { host_mode },
+            worktree,
+            Some(language_server_id),
+        )?;
+
+        let mut command = Self::cmd(
+            roslynls_path,
+            dotnet_path,
+            language_server_path,
+            sdk_root,
+            workspace,
+            binary_args,
+            extra_arguments,
+        )?;
+
+        command.env = utils::get_command_env(
+            Self::LANGUAGE_SERVER_ID,
+            worktree,
+            dotnet_root.as_deref(),
+            installed_sdks.as_deref(),
+        );
+
+        Ok(command)
+    }
+
+    /// The `project_root` setting, relative to the worktree root or absolute.
+    fn get_project_root(worktree: &zed::Worktree) -> Option<String> {
+        let project_root =
+            utils::get_setting::<String>(Self::LANGUAGE_SERVER_ID, worktree, PROJECT_ROOT_KEY)?;
+        let project_root = Path::new(&worktree.root_path())
+            .join(project_root)
+            .to_string_lossy()
+            .to_string();
+
+        println!("[zed-roslynls] Using configured project root {project_root}");
+
+        Some(project_root)
+    }
+
+    /// Returns the solution or solution filter the wrapper should open: the `solution`
+    /// setting, or the solution named after the project root.
+    fn get_solution(worktree: &zed::Worktree, project_root: &str) -> Result<Option<String>> {
+        if let Some(solution) =
+            utils::get_setting::<String>(Self::LANGUAGE_SERVER_ID, worktree, SOLUTION_KEY)
         {
-            return Self::cmd(
-                roslynls_path,
-                path,
-                worktree.root_path().to_string(),
-                binary_args,
-            );
+            let solution =
+                solution::resolve_configured_solution(worktree, project_root, &solution)?;
+            println!("[zed-roslynls] Using configured solution {solution}");
+            return Ok(Some(solution));
         }
 
-        let executable = utils::get_executable(LANGUAGE_SERVER);
+        let solution = solution::find_solution(worktree, project_root);
 
-        if let Some(path) = worktree.which(executable.as_str()) {
-            return Self::cmd(
-                roslynls_path,
-                path,
-                worktree.root_path().to_string(),
-                binary_args,
-            );
+        match &solution {
+            Some(solution) => println!("[zed-roslynls] Using discovered solution {solution}"),
+            None => println!("[zed-roslynls] No solution named after {project_root} found"),
         }
 
-        if let Some(path) = &self.cached_language_server_path
-            && fs::metadata(path).map_or(false, |stat| stat.is_file())
-        {
-            return Self::cmd(
-                roslynls_path,
-                path.clone(),
-                worktree.root_path().to_string(),
-                binary_args,
-            );
+        Ok(solution)
+    }
+
+    /// Returns what the wrapper should load: the projects picked by a solution filter or
+    /// the `include_projects` and `exclude_projects` globs, or else the solution.
+    fn get_workspace(
+        worktree: &zed::Worktree,
+        project_root: Option<&str>,
+    ) -> Result<Option<Workspace>> {
+        let solution = Self::get_solution(worktree, project_root.unwrap_or(&worktree.root_path()))?;
+        let include = utils::get_setting::<Vec<String>>(
+            Self::LANGUAGE_SERVER_ID,
+            worktree,
+            INCLUDE_PROJECTS_KEY,
+        )
+        .unwrap_or_default();
+        let exclude = utils::get_setting::<Vec<String>>(
+            Self::LANGUAGE_SERVER_ID,
+            worktree,
+            EXCLUDE_PROJECTS_KEY,
+        )
+        .unwrap_or_default();
+
+        let is_filter = solution
+            .as_deref()
+            .is_some_and(|solution| solution.to_lowercase().ends_with(".slnf"));
+
+        if !is_filter && include.is_empty() && exclude.is_empty() {
+            return Ok(solution.map(Workspace::Solution));
         }
 
-        let binary_path = Self::get_langauge_server_binary_path(executable.as_str());
+        let Some(solution) = solution else {
+            return Err(format!(
+                "{INCLUDE_PROJECTS_KEY} and {EXCLUDE_PROJECTS_KEY} pick projects from a solution, but none was found; set {SOLUTION_KEY}"
+            ));
+        };
 
-        if fs::metadata(binary_path.clone()).map_or(false, |stat| stat.is_file()) {
-            self.cached_language_server_path = Some(binary_path.clone());
+        let projects = solution::read_projects(worktree, &solution)?;
+        let count = projects.len();
+        let projects = solution::filter_projects(projects, &include, &exclude);
 
-            return Self::cmd(
-                roslynls_path,
-                binary_path.clone(),
-                worktree.root_path().to_string(),
-                binary_args,
-            );
+        println!(
+            "[zed-roslynls] Loading {} of the {count} projects of {solution}",
+            projects.len()
+        );
+
+        let worktree_root = worktree.root_path();
+        let projects: Vec<String> = projects
+            .iter()
+            .map(|project| {
+                Path::new(&worktree_root)
+                    .join(project)
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+
+        Ok(Some(Workspace::Projects(solution::write_projects_file(
+            worktree.id(),
+            &projects,
+        )?)))
+    }
+
+    /// Drops a workspace the extension found by itself when the wrapper is not known to
+    /// support passing it, so that older wrappers keep finding the projects themselves.
+    /// Configured workspaces are kept and checked by `check_roslynls_arguments`.
+    fn filter_discovered_workspace(
+        worktree: &zed::Worktree,
+        roslynls_path: &str,
+        workspace: Option<Workspace>,
+    ) -> Option<Workspace> {
+        let discovered = match &workspace {
+            Some(Workspace::Solution(_)) => {
+                utils::get_setting::<String>(Self::LANGUAGE_SERVER_ID, worktree, SOLUTION_KEY)
+                    .is_none()
+            }
+            Some(Workspace::Discover) => true,
+            _ => false,
+        };
+
+        let workspace = workspace?;
+        let argument = workspace.argument();
+        let version = Self::get_roslynls_version(roslynls_path);
+
+        if !discovered || Self::supports_argument(version.as_ref(), argument) {
+            return Some(workspace);
         }
 
-        let binary_path = Self::ensure_language_server()?;
+        println!(
+            "[zed-roslynls] Not passing {argument} to roslynls {}, which may not support it",
+            version
+                .map(|version| version.to_string())
+                .unwrap_or_else(|| "of unknown version".into())
+        );
 
-        self.cached_language_server_path = Some(binary_path.clone());
+        None
+    }
 
-        Self::cmd(
-            roslynls_path,
-            binary_path,
-            worktree.root_path().to_string(),
-            binary_args,
-        )
+    /// The version of a managed roslynls install; a configured wrapper or one on `PATH` has
+    /// no known version.
+    fn get_roslynls_version(roslynls_path: &str) -> Option<semver::Version> {
+        utils::install::get_installed_version(ROSLYNLS, roslynls_path)
+            .and_then(|tag| utils::parse_version(&tag))
     }
 
+    fn supports_argument(version: Option<&semver::Version>, argument: &str) -> bool {
+        version.is_some_and(|version| {
+            ROSLYNLS_ARGUMENTS
+                .iter()
+                .any(|(name, introduced)| *name == argument && version >= introduced)
+        })
+    }
+
+    /// Fails early when the installed wrapper is too old for an argument of `command`,
+    /// instead of letting the wrapper exit with a usage error.
+    fn check_roslynls_arguments(command: &zed::Command) -> Result<()> {
+        let Some(version) = Self::get_roslynls_version(&command.command) else {
+            println!(
+                "[zed-roslynls] Not checking the arguments of {}: its version is unknown",
+                command.command
+            );
+            return Ok(());
+        };
+
+        for argument in command.args.iter().filter(|arg| arg.starts_with("--")) {
+            match ROSLYNLS_ARGUMENTS.iter().find(|(name, _)| name == argument) {
+                Some((_, introduced)) if version < *introduced => {
+                    return Err(format!(
+                        "roslynls {version} does not support {argument}, which needs roslynls {introduced} or newer; update roslynls or remove {ROSLYNLS_VERSION_KEY}"
+                    ));
+                }
+                Some(_) => {}
+                None => println!("[zed-roslynls] roslynls may not support {argument}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the options of the `preset` setting with the user's Roslyn options merged
+    /// over them.
     pub fn configuration_options(
         &self,
         worktree: &zed::Worktree,
@@ -120,217 +414,351 @@ impl Roslyn {
         let settings = LspSettings::for_worktree(Self::LANGUAGE_SERVER_ID, worktree)
             .ok()
             .and_then(|lsp_settings| lsp_settings.settings);
+        let preset =
+            utils::get_setting::<settings::Preset>(Self::LANGUAGE_SERVER_ID, worktree, PRESET_KEY)
+                .unwrap_or_default();
 
-        Ok(settings.map(|user_settings| self.transform_settings_for_roslyn(user_settings)))
+        // Both are keyed by full option keys, so an option the user sets in any syntax
+        // replaces the preset's.
+        let mut roslyn_config = settings::preset_options(preset);
+
+        if let Some(user_settings) = settings {
+            roslyn_config.extend(self.transform_settings_for_roslyn(user_settings));
+        }
+
+        Ok(Some(zed::serde_json::Value::Object(roslyn_config)))
     }
 
     fn transform_settings_for_roslyn(
         &self,
         settings: zed::serde_json::Value,
-    ) -> zed::serde_json::Value {
-        let mut roslyn_config = Map::new();
-
-        if let zed::serde_json::Value::Object(settings_map) = settings {
-            for (key, value) in &settings_map {
-                if key == ROSLYNLS_PATH_KEY {
-                    continue;
-                }
-
-                if key.contains('|') {
-                    // This is already in the language|category format
-                    if let zed::serde_json::Value::Object(nested_settings) = value {
-                        for (nested_key, nested_value) in nested_settings {
-                            // The key already contains the proper format, just add the setting
-                            let roslyn_key = format!("{}.{}", key, nested_key);
-                            roslyn_config.insert(roslyn_key, nested_value.clone());
-                        }
-                    }
-                }
-                // Handle direct roslyn-format settings (fallback for any other format)
-                else if key.contains('.') {
-                    roslyn_config.insert(key.clone(), value.clone());
-                }
-            }
-        }
+    ) -> Map<String, zed::serde_json::Value> {
+        let settings_map = match settings {
+            zed::serde_json::Value::Object(settings_map) => settings_map,
+            _ => Map::new(),
+        };
 
-        zed::serde_json::Value::Object(roslyn_config)
+        settings::transform(settings_map, EXTENSION_SETTING_KEYS)
     }
 
     fn cmd(
         roslynls_path: String,
+        dotnet_path: Option<String>,
         language_server_path: String,
         project_root: String,
+        workspace: Option<Workspace>,
         binary_args: Option<Vec<String>>,
+        extra_arguments: Vec<String>,
     ) -> Result<zed::Command> {
-        let default_args: Vec<String> = vec![
-            "--lsp".into(),
-            language_server_path,
-            "--project-root".into(),
-            project_root,
+        let solution = match &workspace {
+            Some(Workspace::Solution(solution)) => solution.clone(),
+            _ => String::new(),
+        };
+        let variables = [
+            ("languageServerPath", language_server_path.clone()),
+            ("projectRoot", project_root.clone()),
+            ("solution", solution),
         ];
 
-        return Ok(zed::Command {
+        let mut default_args: Vec<String> = match dotnet_path {
+            Some(dotnet_path) => vec![
+                "--lsp".into(),
+                dotnet_path,
+                "--lsp-dll".into(),
+                language_server_path,
+                "--project-root".into(),
+                project_root,
+            ],
+            None => vec![
+                "--lsp".into(),
+                language_server_path,
+                "--project-root".into(),
+                project_root,
+            ],
+        };
+
+        match workspace {
+            Some(Workspace::Solution(solution)) => {
+                default_args.extend(["--solution".into(), solution]);
+            }
+            Some(Workspace::Projects(projects_file)) => {
+                default_args.extend(["--projects-file".into(), projects_file]);
+            }
+            Some(Workspace::Discover) => {
+                default_args.push("--discover-project-root".into());
+            }
+            None => {}
+        }
+
+        Ok(zed::Command {
             command: roslynls_path,
-            args: binary_args.unwrap_or(default_args),
+            args: binary_args
+                .map(|args| expand_arguments(args, &variables))
+                .unwrap_or(default_args)
+                .into_iter()
+                .chain(expand_arguments(extra_arguments, &variables))
+                .collect(),
             env: Default::default(),
-        });
+        })
     }
+}
 
-    fn ensure_roslynls(self: &mut Self, worktree: &zed::Worktree) -> Result<String, String> {
-        let settings = LspSettings::for_worktree(Self::LANGUAGE_SERVER_ID, worktree).ok();
+/// Replaces `${name}` placeholders in user-supplied arguments with the values of
+/// `variables`, so that arguments need not repeat machine-specific paths.
+fn expand_arguments(args: Vec<String>, variables: &[(&str, String)]) -> Vec<String> {
+    args.into_iter()
+        .map(|arg| {
+            variables.iter().fold(arg, |arg, (name, value)| {
+                arg.replace(&format!("${{{name}}}"), value)
+            })
+        })
+        .collect()
+}
 
-        let roslynls_path = settings
-            .as_ref()
-            .and_then(|lsp_settings| lsp_settings.settings.as_ref())
-            .and_then(|lsp_settings| {
-                if let zed::serde_json::Value::Object(settings_map) = lsp_settings {
-                    settings_map.get(ROSLYNLS_PATH_KEY).and_then(|value| {
-                        if let zed::serde_json::Value::String(path) = value {
-                            Some(path.clone())
-                        } else {
-                            None
-                        }
-                    })
-                } else {
-                    None
-                }
-            });
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HostMode {
+    /// Run the RID-specific apphost shipped in the server package.
+    #[default]
+    AppHost,
+    /// Run `Microsoft.CodeAnalysis.LanguageServer.dll` through a `dotnet` host, for systems
+    /// the apphost does not run on.
+    Dotnet,
+}
 
-        println!(
-            "[zed-roslynls] roslynls_path: {}",
-            roslynls_path.clone().unwrap_or("None".to_string())
-        );
+impl HostMode {
+    fn get_file_name(self) -> String {
+        match self {
+            HostMode::AppHost => utils::get_executable(LANGUAGE_SERVER),
+            HostMode::Dotnet => format!("{LANGUAGE_SERVER}.dll"),
+        }
+    }
+}
 
-        let path = if let Some(path) = roslynls_path {
-            path
-        } else if let Some(cached_path) = &self.cached_roslynls_path {
-            cached_path.clone()
-        } else {
-            utils::ensure_github_release(ROSLYNLS, ROSLYNLS_REPO, ROSLYNLS_TAG, || {
-                Self::get_roslynls_package_id()
-            },
-            zed_extension_api::DownloadedFileType::Uncompressed)?
-        };
+/// The roslynls wrapper, published as single-file GitHub release assets.
+struct RoslynlsTool;
 
-        Ok(path)
+impl ManagedTool for RoslynlsTool {
+    fn id(&self) -> &'static str {
+        ROSLYNLS
     }
 
-    fn get_langauge_server_binary_path(executable: &str) -> String {
-        let package_id = Self::get_langauge_server_package_id();
-        let version = Self::get_language_server_latest_version().unwrap();
-        let runtime_identifier = utils::get_runtime_identifier();
-
-        let version_dir = utils::get_version_dir(package_id, version);
+    fn settings_id(&self) -> &'static str {
+        Roslyn::LANGUAGE_SERVER_ID
+    }
 
-        let current_dir = std::env::current_dir()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
+    fn package_source(&self, _worktree: &zed::Worktree) -> PackageSource {
+        PackageSource::GitHubRelease(GitHubRelease {
+            repo: ROSLYNLS_REPO,
+            tag: ROSLYNLS_TAG,
+            file_type: zed::DownloadedFileType::Uncompressed,
+            compatible_versions: Some(ROSLYNLS_COMPATIBLE_VERSIONS),
+        })
+    }
 
-        format!(
-            "{current_dir}/{version_dir}/content/LanguageServer/{runtime_identifier}/{executable}"
-        )
+    fn published_runtime_identifiers(&self) -> &'static [&'static str] {
+        ROSLYNLS_RUNTIME_IDENTIFIERS
     }
 
-    fn ensure_language_server() -> Result<String, String> {
-        let version = Self::get_language_server_latest_version()?;
+    /// The release assets are the executables themselves, so the Windows ones end in `.exe`.
+    fn package_id(&self, runtime_identifier: &str) -> String {
+        if runtime_identifier.starts_with("win-") {
+            format!("{ROSLYNLS}-{runtime_identifier}.exe")
+        } else {
+            format!("{ROSLYNLS}-{runtime_identifier}")
+        }
+    }
 
-        let executable = utils::get_executable(LANGUAGE_SERVER);
+    fn executable_name(&self) -> String {
+        utils::get_executable(ROSLYNLS)
+    }
+}
 
-        let package_id = Self::get_langauge_server_package_id();
+/// The Roslyn Language Server, published as one NuGet package per RID.
+struct LanguageServerTool {
+    host_mode: HostMode,
+}
 
-        let binary_path = Self::get_langauge_server_binary_path(executable.as_str());
+impl ManagedTool for LanguageServerTool {
+    fn id(&self) -> &'static str {
+        Roslyn::LANGUAGE_SERVER_ID
+    }
 
-        let asset_name = utils::get_nuget_asset_name(package_id.clone(), version.clone());
+    fn settings_id(&self) -> &'static str {
+        Roslyn::LANGUAGE_SERVER_ID
+    }
 
-        let url = format!(
-            "https://pkgs.dev.azure.com/{ORGANIZATION}/{PROJECT}/_packaging/{FEED}/nuget/v3/flat2/{package_id}/{version}/{asset_name}"
-        );
+    fn package_source(&self, worktree: &zed::Worktree) -> PackageSource {
+        match utils::get_setting::<String>(Roslyn::LANGUAGE_SERVER_ID, worktree, LOCAL_FEED_KEY) {
+            Some(local_feed) => PackageSource::NuGet(NuGetFeed::Local(local_feed)),
+            None => PackageSource::NuGet(NuGetFeed::AzureArtifacts {
+                organization: ORGANIZATION,
+                project: PROJECT,
+                feed: FEED,
+            }),
+        }
+    }
 
-        println!(
-            "[zed-roslynls] Downloading Roslyn Language Server from: {}",
-            url.clone()
-        );
+    fn published_runtime_identifiers(&self) -> &'static [&'static str] {
+        LANGUAGE_SERVER_RUNTIME_IDENTIFIERS
+    }
 
-        let version_dir = utils::get_version_dir(package_id, version);
+    fn package_id(&self, runtime_identifier: &str) -> String {
+        format!("{LANGUAGE_SERVER}.{runtime_identifier}")
+    }
 
-        zed::download_file(&url, &version_dir, zed::DownloadedFileType::Zip)
-            .map_err(|e| format!("failed to download file: {e}"))?;
+    fn executable_name(&self) -> String {
+        self.host_mode.get_file_name()
+    }
 
-        let entries =
-            fs::read_dir(".").map_err(|e| format!("failed to list working directory {e}"))?;
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("failed to load directory entry {e}"))?;
-            if entry.file_name().to_str() != Some(&version_dir) {
-                fs::remove_dir_all(entry.path()).ok();
-            }
-        }
+    fn relative_executable(&self, runtime_identifier: &str) -> String {
+        format!(
+            "content/LanguageServer/{runtime_identifier}/{}",
+            self.executable_name()
+        )
+    }
 
-        let entries = fs::read_dir(&version_dir)
-            .map_err(|e| format!("failed to list version directory {e}"))?;
-        let mut q = std::collections::VecDeque::from_iter(entries);
-        while !q.is_empty() {
-            let entry = q.pop_front().unwrap();
-            let entry = entry.map_err(|e| format!("failed to load directory entry {e}"))?;
-            let filetype = entry
-                .file_type()
-                .map_err(|e| format!("failed to get file type {e}"))?;
-            if filetype.is_dir() {
-                let sub_entries = fs::read_dir(entry.path())
-                    .map_err(|e| format!("failed to list sub-directory {e}"))?;
-                for sub_entry in sub_entries {
-                    q.push_back(sub_entry);
-                }
-            } else if filetype.is_file() {
-                zed::make_file_executable(entry.path().to_str().unwrap())
-                    .map_err(|e| format!("failed to make file executable {e}"))?;
+    /// The framework-dependent server can also run from the platform-neutral package.
+    fn runtime_identifiers(&self, worktree: &zed::Worktree) -> Result<Vec<String>> {
+        let mut runtime_identifiers = utils::get_tool_runtime_identifiers(
+            LANGUAGE_SERVER,
+            self.settings_id(),
+            worktree,
+            self.published_runtime_identifiers(),
+        )?;
+
+        match self.host_mode {
+            HostMode::AppHost if runtime_identifiers.is_empty() => {
+                return Err(format!(
+                    "no {LANGUAGE_SERVER} package is published for {}, try \"host_mode\": \"dotnet\"",
+                    utils::get_runtime_identifiers().join(", ")
+                ));
             }
+            HostMode::AppHost => {}
+            HostMode::Dotnet => runtime_identifiers.push(NEUTRAL_RUNTIME_IDENTIFIER.into()),
         }
 
-        Ok(binary_path)
+        Ok(runtime_identifiers)
     }
 
-    fn get_langauge_server_package_id() -> String {
-        let runtime_identifier = utils::get_runtime_identifier();
-
-        format!("{LANGUAGE_SERVER}.{runtime_identifier}")
+    fn use_path(&self) -> bool {
+        self.host_mode == HostMode::AppHost
     }
 
-    fn get_roslynls_package_id() -> String {
-        let runtime_identifier = utils::get_runtime_identifier();
+    /// Reuses a server installed by another tool, unless a local feed is configured or a
+    /// RID is forced: those installs are built for the detected RID. A pinned version
+    /// always comes from the extension's own install, whatever version is installed
+    /// elsewhere.
+    fn find_existing(&self, worktree: &zed::Worktree) -> Option<String> {
+        let discover_installs =
+            utils::get_setting::<bool>(Roslyn::LANGUAGE_SERVER_ID, worktree, DISCOVER_INSTALLS_KEY)
+                .unwrap_or(true);
+
+        if !discover_installs
+            || utils::get_setting::<String>(Roslyn::LANGUAGE_SERVER_ID, worktree, LOCAL_FEED_KEY)
+                .is_some()
+            || utils::get_runtime_identifier_override(Roslyn::LANGUAGE_SERVER_ID, worktree)
+                .is_some()
+        {
+            return None;
+        }
 
-        format!("{ROSLYNLS}-{runtime_identifier}")
-    }
+        if let Some(version) = utils::get_setting::<String>(
+            Roslyn::LANGUAGE_SERVER_ID,
+            worktree,
+            &format!("{}_version", self.id()),
+        ) {
+            println!(
+                "[zed-roslynls] Roslyn Language Server is pinned to {version}, not looking for existing installs"
+            );
+            return None;
+        }
 
-    fn get_language_server_latest_version() -> Result<String, String> {
-        let package_id = Self::get_langauge_server_package_id();
-        let url = format!(
-            "https://feeds.dev.azure.com/{ORGANIZATION}/{PROJECT}/_apis/packaging/feeds/{FEED}/packages?packageNameQuery={package_id}&api-version=6.0-preview.1",
-        );
+        let install = discovery::discover_language_server(worktree, &self.executable_name())?;
 
         println!(
-            "[zed-roslynls] Fetching latest Roslyn Language Server version from: {}",
-            url.clone()
+            "[zed-roslynls] Using Roslyn Language Server {} from {}: {}",
+            install.version, install.source, install.path
         );
 
-        let request = zed::http_client::HttpRequest::builder()
-            .method(zed_extension_api::http_client::HttpMethod::Get)
-            .url(&url)
-            .build()?;
-        let nuget_package_response = zed::http_client::fetch(&request)?;
+        Some(install.path)
+    }
+}
 
-        let nuget_packages: NuGetPackagesResponse =
-            serde_json::from_slice(&nuget_package_response.body.as_slice())
-                .map_err(|e| e.to_string())?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let package = nuget_packages
-            .value
+    fn expand(args: &[&str], variables: &[(&str, &str)]) -> Vec<String> {
+        let variables: Vec<(&str, String)> = variables
             .iter()
-            .flat_map(|p| p.versions.iter())
-            .find(|v| v.is_latest == true)
-            .unwrap();
+            .map(|(name, value)| (*name, value.to_string()))
+            .collect();
+
+        expand_arguments(args.iter().map(|arg| arg.to_string()).collect(), &variables)
+    }
 
-        Ok(package.version.clone())
+    #[test]
+    fn test_expand_each_placeholder() {
+        let variables = [
+            ("languageServerPath", "/servers/roslyn"),
+            ("projectRoot", "/src/app"),
+            ("solution", "/src/app/App.sln"),
+        ];
+
+        assert_eq!(
+            expand(
+                &[
+                    "--lsp",
+                    "${languageServerPath}",
+                    "--log-file-path",
+                    "${projectRoot}/.roslynls.log",
+                    "--solution=${solution}",
+                    "${projectRoot}:${projectRoot}",
+                ],
+                &variables
+            ),
+            [
+                "--lsp",
+                "/servers/roslyn",
+                "--log-file-path",
+                "/src/app/.roslynls.log",
+                "--solution=/src/app/App.sln",
+                "/src/app:/src/app",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_unknown_placeholders() {
+        assert_eq!(
+            expand(
+                &[
+                    "${unknown}",
+                    "$projectRoot",
+                    "${projectroot}",
+                    "${projectRoot"
+                ],
+                &[("projectRoot", "/src/app")]
+            ),
+            [
+                "${unknown}",
+                "$projectRoot",
+                "${projectroot}",
+                "${projectRoot"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_empty_values() {
+        assert_eq!(
+            expand(
+                &["--solution", "${solution}", "${solution}/x"],
+                &[("solution", "")]
+            ),
+            ["--solution", "", "/x"]
+        );
+        assert!(expand(&[], &[("solution", "")]).is_empty());
     }
 }
@@ -0,0 +1,891 @@
+use serde::Deserialize;
+use zed_extension_api::serde_json::{Map, Value, json};
+
+/// The languages Roslyn reads options for, the part of an option key before `|`.
+const LANGUAGES: &[&str] = &["csharp", "visual_basic"];
+/// The top-level keys of the nested syntax that split options like VS Code's `dotnet.*`
+/// and `csharp.*` settings.
+const FRIENDLY_LANGUAGES: &[&str] = &["dotnet", "csharp"];
+/// The language the nested syntax configures.
+const FRIENDLY_TARGET_LANGUAGE: &str = "csharp";
+
+/// The type of a Roslyn option, with its default.
+#[derive(Debug, Clone, Copy)]
+pub enum OptionType {
+    Bool(bool),
+    Integer(i64),
+    String(&'static str),
+    /// A string that must be one of the values, the first of which is the default.
+    Enum(&'static [&'static str]),
+}
+
+impl OptionType {
+    pub fn default_value(self) -> Value {
+        match self {
+            OptionType::Bool(default) => Value::Bool(default),
+            OptionType::Integer(default) => Value::from(default),
+            OptionType::String(default) => Value::from(default),
+            OptionType::Enum(values) => Value::from(values[0]),
+        }
+    }
+}
+
+/// A Roslyn option: `<language>|<group>.<name>` for an option that is set per language,
+/// and `<group>.<name>` for a global one.
+pub struct RoslynOption {
+    pub group: &'static str,
+    pub name: &'static str,
+    pub option_type: OptionType,
+    pub per_language: bool,
+}
+
+impl RoslynOption {
+    /// The key the server reads the option from for `language`.
+    pub fn key(&self, language: &str) -> String {
+        if self.per_language {
+            format!("{language}|{}.{}", self.group, self.name)
+        } else {
+            format!("{}.{}", self.group, self.name)
+        }
+    }
+}
+
+const fn option(group: &'static str, name: &'static str, option_type: OptionType) -> RoslynOption {
+    RoslynOption {
+        group,
+        name,
+        option_type,
+        per_language: true,
+    }
+}
+
+const fn global_option(
+    group: &'static str,
+    name: &'static str,
+    option_type: OptionType,
+) -> RoslynOption {
+    RoslynOption {
+        group,
+        name,
+        option_type,
+        per_language: false,
+    }
+}
+
+const DIAGNOSTICS_SCOPES: &[&str] = &["openFiles", "fullSolution", "none", "default"];
+
+/// The options the Roslyn language server reads through `workspace/configuration`, with
+/// the defaults the server uses when they are not set.
+pub const ROSLYN_OPTIONS: &[RoslynOption] = &[
+    // Inlay hints
+    option(
+        "inlay_hints",
+        "dotnet_enable_inlay_hints_for_parameters",
+        OptionType::Bool(false),
+    ),
+    option(
+        "inlay_hints",
+        "dotnet_enable_inlay_hints_for_literal_parameters",
+        OptionType::Bool(false),
+    ),
+    option(
+        "inlay_hints",
+        "dotnet_enable_inlay_hints_for_indexer_parameters",
+        OptionType::Bool(false),
+    ),
+    option(
+        "inlay_hints",
+        "dotnet_enable_inlay_hints_for_object_creation_parameters",
+        OptionType::Bool(false),
+    ),
+    option(
+        "inlay_hints",
+        "dotnet_enable_inlay_hints_for_other_parameters",
+        OptionType::Bool(false),
+    ),
+    option(
+        "inlay_hints",
+        "dotnet_suppress_inlay_hints_for_parameters_that_differ_only_by_suffix",
+        OptionType::Bool(true),
+    ),
+    option(
+        "inlay_hints",
+        "dotnet_suppress_inlay_hints_for_parameters_that_match_method_intent",
+        OptionType::Bool(true),
+    ),
+    option(
+        "inlay_hints",
+        "dotnet_suppress_inlay_hints_for_parameters_that_match_argument_name",
+        OptionType::Bool(true),
+    ),
+    option(
+        "inlay_hints",
+        "csharp_enable_inlay_hints_for_types",
+        OptionType::Bool(false),
+    ),
+    option(
+        "inlay_hints",
+        "csharp_enable_inlay_hints_for_implicit_variable_types",
+        OptionType::Bool(false),
+    ),
+    option(
+        "inlay_hints",
+        "csharp_enable_inlay_hints_for_lambda_parameter_types",
+        OptionType::Bool(false),
+    ),
+    option(
+        "inlay_hints",
+        "csharp_enable_inlay_hints_for_implicit_object_creation",
+        OptionType::Bool(false),
+    ),
+    option(
+        "inlay_hints",
+        "csharp_enable_inlay_hints_for_collection_expressions",
+        OptionType::Bool(false),
+    ),
+    // Code lens
+    option(
+        "code_lens",
+        "dotnet_enable_references_code_lens",
+        OptionType::Bool(true),
+    ),
+    option(
+        "code_lens",
+        "dotnet_enable_tests_code_lens",
+        OptionType::Bool(true),
+    ),
+    // Background analysis
+    option(
+        "background_analysis",
+        "dotnet_analyzer_diagnostics_scope",
+        OptionType::Enum(DIAGNOSTICS_SCOPES),
+    ),
+    option(
+        "background_analysis",
+        "dotnet_compiler_diagnostics_scope",
+        OptionType::Enum(DIAGNOSTICS_SCOPES),
+    ),
+    // Formatting
+    option(
+        "formatting",
+        "dotnet_organize_imports_on_format",
+        OptionType::Bool(false),
+    ),
+    option(
+        "code_style.formatting.indentation_and_spacing",
+        "indent_size",
+        OptionType::Integer(4),
+    ),
+    option(
+        "code_style.formatting.indentation_and_spacing",
+        "indent_style",
+        OptionType::Enum(&["space", "tab"]),
+    ),
+    option(
+        "code_style.formatting.indentation_and_spacing",
+        "tab_width",
+        OptionType::Integer(4),
+    ),
+    option(
+        "code_style.formatting.new_line",
+        "end_of_line",
+        OptionType::Enum(&["\n", "\r\n"]),
+    ),
+    option(
+        "code_style.formatting.new_line",
+        "insert_final_newline",
+        OptionType::Bool(false),
+    ),
+    option(
+        "auto_insert",
+        "dotnet_enable_auto_insert",
+        OptionType::Bool(true),
+    ),
+    // Completion
+    option(
+        "completion",
+        "dotnet_show_name_completion_suggestions",
+        OptionType::Bool(true),
+    ),
+    option(
+        "completion",
+        "dotnet_provide_regex_completions",
+        OptionType::Bool(true),
+    ),
+    option(
+        "completion",
+        "dotnet_show_completion_items_from_unimported_namespaces",
+        OptionType::Bool(true),
+    ),
+    option(
+        "completion",
+        "dotnet_trigger_completion_in_argument_lists",
+        OptionType::Bool(true),
+    ),
+    // Navigation
+    global_option(
+        "navigation",
+        "dotnet_navigate_to_decompiled_sources",
+        OptionType::Bool(true),
+    ),
+    global_option(
+        "navigation",
+        "dotnet_navigate_to_source_link_and_embedded_sources",
+        OptionType::Bool(true),
+    ),
+    option(
+        "symbol_search",
+        "dotnet_search_reference_assemblies",
+        OptionType::Bool(true),
+    ),
+    option(
+        "quick_info",
+        "dotnet_show_remarks_in_quick_info",
+        OptionType::Bool(true),
+    ),
+    option(
+        "highlighting",
+        "dotnet_highlight_related_json_components",
+        OptionType::Bool(true),
+    ),
+    option(
+        "highlighting",
+        "dotnet_highlight_related_regex_components",
+        OptionType::Bool(true),
+    ),
+    // Code generation
+    option(
+        "implement_type",
+        "dotnet_insertion_behavior",
+        OptionType::Enum(&["withOtherMembersOfTheSameKind", "atTheEnd"]),
+    ),
+    option(
+        "implement_type",
+        "dotnet_property_generation_behavior",
+        OptionType::Enum(&["preferThrowingProperties", "preferAutoProperties"]),
+    ),
+    // Projects
+    global_option("projects", "dotnet_binary_log_path", OptionType::String("")),
+    global_option(
+        "projects",
+        "dotnet_enable_automatic_restore",
+        OptionType::Bool(true),
+    ),
+    global_option(
+        "projects",
+        "dotnet_enable_file_based_programs",
+        OptionType::Bool(true),
+    ),
+];
+
+/// Short paths of the nested syntax, next to the paths derived from the option keys, as
+/// `(path, "<group>.<name>")`.
+const FRIENDLY_ALIASES: &[(&str, &str)] = &[
+    (
+        "inlayHints.types",
+        "inlay_hints.csharp_enable_inlay_hints_for_types",
+    ),
+    (
+        "inlayHints.implicitVariableTypes",
+        "inlay_hints.csharp_enable_inlay_hints_for_implicit_variable_types",
+    ),
+    (
+        "inlayHints.lambdaParameterTypes",
+        "inlay_hints.csharp_enable_inlay_hints_for_lambda_parameter_types",
+    ),
+    (
+        "inlayHints.implicitObjectCreation",
+        "inlay_hints.csharp_enable_inlay_hints_for_implicit_object_creation",
+    ),
+    (
+        "inlayHints.collectionExpressions",
+        "inlay_hints.csharp_enable_inlay_hints_for_collection_expressions",
+    ),
+    (
+        "inlayHints.parameters",
+        "inlay_hints.dotnet_enable_inlay_hints_for_parameters",
+    ),
+    (
+        "inlayHints.parameters.literals",
+        "inlay_hints.dotnet_enable_inlay_hints_for_literal_parameters",
+    ),
+    (
+        "inlayHints.parameters.indexers",
+        "inlay_hints.dotnet_enable_inlay_hints_for_indexer_parameters",
+    ),
+    (
+        "inlayHints.parameters.objectCreation",
+        "inlay_hints.dotnet_enable_inlay_hints_for_object_creation_parameters",
+    ),
+    (
+        "inlayHints.parameters.other",
+        "inlay_hints.dotnet_enable_inlay_hints_for_other_parameters",
+    ),
+    (
+        "inlayHints.parameters.suppressWhenDifferingOnlyBySuffix",
+        "inlay_hints.dotnet_suppress_inlay_hints_for_parameters_that_differ_only_by_suffix",
+    ),
+    (
+        "inlayHints.parameters.suppressWhenMatchingMethodIntent",
+        "inlay_hints.dotnet_suppress_inlay_hints_for_parameters_that_match_method_intent",
+    ),
+    (
+        "inlayHints.parameters.suppressWhenMatchingArgumentName",
+        "inlay_hints.dotnet_suppress_inlay_hints_for_parameters_that_match_argument_name",
+    ),
+    (
+        "codeLens.references",
+        "code_lens.dotnet_enable_references_code_lens",
+    ),
+    ("codeLens.tests", "code_lens.dotnet_enable_tests_code_lens"),
+    (
+        "formatting.indentSize",
+        "code_style.formatting.indentation_and_spacing.indent_size",
+    ),
+    (
+        "formatting.indentStyle",
+        "code_style.formatting.indentation_and_spacing.indent_style",
+    ),
+    (
+        "formatting.tabWidth",
+        "code_style.formatting.indentation_and_spacing.tab_width",
+    ),
+    (
+        "formatting.endOfLine",
+        "code_style.formatting.new_line.end_of_line",
+    ),
+    (
+        "formatting.insertFinalNewline",
+        "code_style.formatting.new_line.insert_final_newline",
+    ),
+    (
+        "autoInsert.enabled",
+        "auto_insert.dotnet_enable_auto_insert",
+    ),
+];
+
+/// A built-in set of Roslyn options that user settings are merged over.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    /// No options: the server's own defaults.
+    Minimal,
+    /// Parameter and type inlay hints, compiler diagnostics for the whole solution, and
+    /// completion from unimported namespaces.
+    #[default]
+    Default,
+    /// Everything in `Default`, plus all inlay hints, analyzer diagnostics for the whole
+    /// solution and organizing imports on format.
+    Full,
+}
+
+/// Returns the Roslyn options of `preset`, keyed like [`transform`]'s output.
+pub fn preset_options(preset: Preset) -> Map<String, Value> {
+    let default = [
+        (
+            "inlay_hints.dotnet_enable_inlay_hints_for_parameters",
+            json!(true),
+        ),
+        (
+            "inlay_hints.dotnet_enable_inlay_hints_for_literal_parameters",
+            json!(true),
+        ),
+        (
+            "inlay_hints.csharp_enable_inlay_hints_for_types",
+            json!(true),
+        ),
+        (
+            "inlay_hints.csharp_enable_inlay_hints_for_implicit_variable_types",
+            json!(true),
+        ),
+        (
+            "inlay_hints.csharp_enable_inlay_hints_for_lambda_parameter_types",
+            json!(true),
+        ),
+        (
+            "background_analysis.dotnet_compiler_diagnostics_scope",
+            json!("fullSolution"),
+        ),
+        (
+            "completion.dotnet_show_completion_items_from_unimported_namespaces",
+            json!(true),
+        ),
+        (
+            "navigation.dotnet_navigate_to_decompiled_sources",
+            json!(true),
+        ),
+    ];
+    let full = [
+        (
+            "inlay_hints.dotnet_enable_inlay_hints_for_indexer_parameters",
+            json!(true),
+        ),
+        (
+            "inlay_hints.dotnet_enable_inlay_hints_for_object_creation_parameters",
+            json!(true),
+        ),
+        (
+            "inlay_hints.dotnet_enable_inlay_hints_for_other_parameters",
+            json!(true),
+        ),
+        (
+            "inlay_hints.csharp_enable_inlay_hints_for_implicit_object_creation",
+            json!(true),
+        ),
+        (
+            "inlay_hints.csharp_enable_inlay_hints_for_collection_expressions",
+            json!(true),
+        ),
+        (
+            "background_analysis.dotnet_analyzer_diagnostics_scope",
+            json!("fullSolution"),
+        ),
+        ("formatting.dotnet_organize_imports_on_format", json!(true)),
+    ];
+
+    let options: Vec<_> = match preset {
+        Preset::Minimal => Vec::new(),
+        Preset::Default => default.into_iter().collect(),
+        Preset::Full => default.into_iter().chain(full).collect(),
+    };
+
+    options
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let option = ROSLYN_OPTIONS
+                .iter()
+                .find(|option| key == format!("{}.{}", option.group, option.name))?;
+            Some((option.key(FRIENDLY_TARGET_LANGUAGE), value))
+        })
+        .collect()
+}
+
+/// Converts the user's settings into Roslyn's `<language>|<group>.<name>` and
+/// `<group>.<name>` keys and checks them against [`ROSLYN_OPTIONS`].
+///
+/// Settings are either full keys, `<language>|<group>` objects of options, or the nested
+/// syntax of [`friendly_paths`], optionally under a `dotnet` key, which sets per-language
+/// options for all languages, or a `csharp` key, which sets them for C# only. Global
+/// options are set for all languages either way. Unknown keys
+/// are still passed on, since the server may know options this list does not, but they
+/// are logged with the closest known key; values of the wrong type are dropped so that
+/// the server uses its default.
+pub fn transform(settings: Map<String, Value>, extension_keys: &[&str]) -> Map<String, Value> {
+    let mut roslyn_config = Map::new();
+
+    for (key, value) in settings {
+        if extension_keys.contains(&key.as_str()) {
+            continue;
+        }
+
+        match value {
+            // Group names can contain dots too, but no option value is an object.
+            Value::Object(options) if key.contains('|') && find_option(&key).is_none() => {
+                if !is_known_group(&key) {
+                    warn_unknown(&key, known_groups(), "Roslyn setting group");
+                }
+
+                for (name, value) in options {
+                    insert_option(&mut roslyn_config, format!("{key}.{name}"), value);
+                }
+            }
+            value if key.contains('|') || find_option(&key).is_some() => {
+                insert_option(&mut roslyn_config, key, value);
+            }
+            value if is_friendly_root(&key) => {
+                // `dotnet.*` options apply to every language, `csharp.*` ones to C# only,
+                // as in VS Code.
+                let languages = match key.as_str() {
+                    "dotnet" => LANGUAGES,
+                    _ => &[FRIENDLY_TARGET_LANGUAGE],
+                };
+                let prefix = if FRIENDLY_LANGUAGES.contains(&key.as_str()) {
+                    String::new()
+                } else {
+                    key
+                };
+
+                let mut leaves = Vec::new();
+                flatten(prefix, value, &mut leaves);
+
+                for (path, value) in leaves {
+                    // Global options can also be grouped like `"projects": { ... }`.
+                    match find_friendly_option(&path).or_else(|| find_option(&path)) {
+                        Some(option) if option.per_language => {
+                            for language in languages {
+                                insert_option(
+                                    &mut roslyn_config,
+                                    option.key(language),
+                                    value.clone(),
+                                );
+                            }
+                        }
+                        Some(option) => insert_option(&mut roslyn_config, option.key(""), value),
+                        None => warn_unknown(&path, all_friendly_paths(), "setting"),
+                    }
+                }
+            }
+            value if key.contains('.') => {
+                warn_unknown(&key, known_keys(), "Roslyn setting");
+                roslyn_config.insert(key, value);
+            }
+            _ => {
+                let candidates = extension_keys
+                    .iter()
+                    .map(|key| key.to_string())
+                    .chain(known_groups())
+                    .chain(friendly_roots());
+                warn_unknown(&key, candidates, "setting");
+            }
+        }
+    }
+
+    roslyn_config
+}
+
+fn insert_option(roslyn_config: &mut Map<String, Value>, key: String, value: Value) {
+    let Some(option) = find_option(&key) else {
+        warn_unknown(&key, known_keys(), "Roslyn setting");
+        roslyn_config.insert(key, value);
+        return;
+    };
+
+    if let Err(expected) = check_value(option.option_type, &value) {
+        println!(
+            "[zed-roslynls] Ignoring {key}: expected {expected}, got {value}; the server uses its default {}",
+            option.option_type.default_value()
+        );
+        return;
+    }
+
+    roslyn_config.insert(key, value);
+}
+
+fn check_value(option_type: OptionType, value: &Value) -> Result<(), String> {
+    let valid = match option_type {
+        OptionType::Bool(_) => value.is_boolean(),
+        OptionType::Integer(_) => value.is_i64(),
+        OptionType::String(_) => value.is_string(),
+        OptionType::Enum(values) => value.as_str().is_some_and(|value| values.contains(&value)),
+    };
+
+    if valid {
+        return Ok(());
+    }
+
+    Err(match option_type {
+        OptionType::Bool(_) => "true or false".into(),
+        OptionType::Integer(_) => "an integer".into(),
+        OptionType::String(_) => "a string".into(),
+        OptionType::Enum(values) => format!("one of {}", format_values(values)),
+    })
+}
+
+fn format_values(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|value| format!("{value:?}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn find_option(key: &str) -> Option<&'static RoslynOption> {
+    match key.split_once('|') {
+        Some((language, _)) if !LANGUAGES.contains(&language) => None,
+        Some((language, _)) => ROSLYN_OPTIONS
+            .iter()
+            .find(|option| option.per_language && option.key(language) == key),
+        None => ROSLYN_OPTIONS
+            .iter()
+            .find(|option| !option.per_language && option.key("") == key),
+    }
+}
+
+/// Flattens nested objects into dotted paths. Arrays and other values are leaves, since
+/// option values are never objects.
+fn flatten(path: String, value: Value, leaves: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if path.is_empty() {
+                    key
+                } else {
+                    format!("{path}.{key}")
+                };
+                flatten(path, value, leaves);
+            }
+        }
+        value => leaves.push((path, value)),
+    }
+}
+
+/// The paths of an option in the nested syntax: its group and name in camel case, without
+/// the `dotnet_` or `csharp_` prefix, as in VS Code's settings (`inlayHints.enableInlayHintsForTypes`),
+/// and its short aliases.
+fn friendly_paths(option: &RoslynOption) -> impl Iterator<Item = String> {
+    let name = option
+        .name
+        .strip_prefix("dotnet_")
+        .or_else(|| option.name.strip_prefix("csharp_"))
+        .unwrap_or(option.name);
+    let group: Vec<String> = option.group.split('.').map(to_camel_case).collect();
+    let key = format!("{}.{}", option.group, option.name);
+
+    std::iter::once(format!("{}.{}", group.join("."), to_camel_case(name))).chain(
+        FRIENDLY_ALIASES
+            .iter()
+            .filter(move |(_, alias_key)| *alias_key == key)
+            .map(|(path, _)| path.to_string()),
+    )
+}
+
+fn all_friendly_paths() -> impl Iterator<Item = String> {
+    ROSLYN_OPTIONS.iter().flat_map(friendly_paths)
+}
+
+fn friendly_roots() -> impl Iterator<Item = String> {
+    FRIENDLY_LANGUAGES
+        .iter()
+        .map(|language| language.to_string())
+        .chain(all_friendly_paths().filter_map(|path| path.split('.').next().map(String::from)))
+}
+
+fn is_friendly_root(key: &str) -> bool {
+    let root = key.split('.').next().unwrap_or(key);
+    friendly_roots().any(|friendly_root| friendly_root == root)
+}
+
+/// Finds the option at `path` in the nested syntax, where `<path>.enabled` is the same as
+/// `<path>`, so that an option can be set next to the options nested under it.
+fn find_friendly_option(path: &str) -> Option<&'static RoslynOption> {
+    let find = |path: &str| {
+        ROSLYN_OPTIONS
+            .iter()
+            .find(|option| friendly_paths(option).any(|friendly_path| friendly_path == path))
+    };
+
+    find(path).or_else(|| find(path.strip_suffix(".enabled")?))
+}
+
+fn to_camel_case(snake_case: &str) -> String {
+    let mut words = snake_case.split('_');
+    let first = words.next().unwrap_or_default().to_string();
+
+    words.fold(first, |mut camel_case, word| {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel_case.extend(first.to_uppercase());
+            camel_case.push_str(chars.as_str());
+        }
+        camel_case
+    })
+}
+
+fn is_known_group(key: &str) -> bool {
+    known_groups().any(|group| group == key)
+}
+
+fn known_groups() -> impl Iterator<Item = String> {
+    LANGUAGES.iter().flat_map(|language| {
+        ROSLYN_OPTIONS
+            .iter()
+            .filter(|option| option.per_language)
+            .map(move |option| format!("{language}|{}", option.group))
+    })
+}
+
+fn known_keys() -> impl Iterator<Item = String> {
+    LANGUAGES
+        .iter()
+        .flat_map(|language| {
+            ROSLYN_OPTIONS
+                .iter()
+                .filter(|option| option.per_language)
+                .map(move |option| option.key(language))
+        })
+        .chain(
+            ROSLYN_OPTIONS
+                .iter()
+                .filter(|option| !option.per_language)
+                .map(|option| option.key("")),
+        )
+}
+
+fn warn_unknown(key: &str, candidates: impl Iterator<Item = String>, kind: &str) {
+    match find_closest(key, candidates) {
+        Some(closest) => {
+            println!("[zed-roslynls] Unknown {kind} {key}, did you mean {closest}?")
+        }
+        None => println!("[zed-roslynls] Unknown {kind} {key}"),
+    }
+}
+
+/// Returns the candidate with the smallest edit distance to `key`, if it is close enough
+/// to be a misspelling of it.
+fn find_closest(key: &str, candidates: impl Iterator<Item = String>) -> Option<String> {
+    candidates
+        .map(|candidate| (edit_distance(key, &candidate), candidate))
+        .min_by_key(|(distance, _)| *distance)
+        .filter(|(distance, _)| *distance <= key.chars().count().div_ceil(3))
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between `a` and `b`, ignoring case.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform_json(settings: Value) -> Value {
+        let Value::Object(settings) = settings else {
+            panic!("settings must be an object");
+        };
+
+        Value::Object(transform(settings, &["preset"]))
+    }
+
+    #[test]
+    fn test_full_keys() {
+        assert_eq!(
+            transform_json(json!({
+                "csharp|inlay_hints.csharp_enable_inlay_hints_for_types": true,
+                "navigation.dotnet_navigate_to_decompiled_sources": false,
+                "preset": "full",
+            })),
+            json!({
+                "csharp|inlay_hints.csharp_enable_inlay_hints_for_types": true,
+                "navigation.dotnet_navigate_to_decompiled_sources": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_global_options_have_no_language() {
+        assert!(find_option("navigation.dotnet_navigate_to_decompiled_sources").is_some());
+        assert!(find_option("csharp|navigation.dotnet_navigate_to_decompiled_sources").is_none());
+        assert!(find_option("csharp|inlay_hints.csharp_enable_inlay_hints_for_types").is_some());
+        assert!(find_option("inlay_hints.csharp_enable_inlay_hints_for_types").is_none());
+    }
+
+    #[test]
+    fn test_groups() {
+        assert_eq!(
+            transform_json(json!({
+                "csharp|code_style.formatting.indentation_and_spacing": { "indent_size": 2 },
+                "projects": { "dotnet_enable_automatic_restore": false },
+            })),
+            json!({
+                "csharp|code_style.formatting.indentation_and_spacing.indent_size": 2,
+                "projects.dotnet_enable_automatic_restore": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_nested_syntax() {
+        assert_eq!(
+            transform_json(json!({
+                "inlayHints": {
+                    "types": true,
+                    "parameters": { "enabled": true, "literals": false },
+                },
+                "dotnet": {
+                    "navigation": { "navigateToDecompiledSources": false },
+                    "projects": { "binaryLogPath": "/tmp/roslyn.binlog" },
+                    "codeLens": { "references": false },
+                },
+                "csharp": {
+                    "formatting": { "indentSize": 2 },
+                },
+            })),
+            json!({
+                "csharp|inlay_hints.csharp_enable_inlay_hints_for_types": true,
+                "csharp|inlay_hints.dotnet_enable_inlay_hints_for_parameters": true,
+                "csharp|inlay_hints.dotnet_enable_inlay_hints_for_literal_parameters": false,
+                "navigation.dotnet_navigate_to_decompiled_sources": false,
+                "projects.dotnet_binary_log_path": "/tmp/roslyn.binlog",
+                "csharp|code_lens.dotnet_enable_references_code_lens": false,
+                "visual_basic|code_lens.dotnet_enable_references_code_lens": false,
+                "csharp|code_style.formatting.indentation_and_spacing.indent_size": 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_dotnet_options_apply_to_visual_basic() {
+        let config = transform_json(json!({
+            "dotnet": { "codeLens": { "references": false } },
+            "csharp": { "inlayHints": { "parameters": true } },
+        }));
+
+        assert_eq!(
+            config.get("visual_basic|code_lens.dotnet_enable_references_code_lens"),
+            Some(&json!(false))
+        );
+        assert_eq!(
+            config.get("visual_basic|inlay_hints.dotnet_enable_inlay_hints_for_parameters"),
+            None
+        );
+        assert_eq!(
+            config.get("csharp|inlay_hints.dotnet_enable_inlay_hints_for_parameters"),
+            Some(&json!(true))
+        );
+    }
+
+    #[test]
+    fn test_wrong_types_are_dropped() {
+        assert_eq!(
+            transform_json(json!({
+                "csharp|inlay_hints.csharp_enable_inlay_hints_for_types": "yes",
+                "csharp|background_analysis.dotnet_analyzer_diagnostics_scope": "everything",
+                "csharp|unknown.option": 1,
+            })),
+            json!({ "csharp|unknown.option": 1 })
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        assert!(preset_options(Preset::Minimal).is_empty());
+
+        let default = preset_options(Preset::Default);
+        let full = preset_options(Preset::Full);
+
+        assert_eq!(default.len(), 8);
+        assert_eq!(full.len(), 15);
+        assert!(default.keys().all(|key| full.contains_key(key)));
+        assert!(full.keys().all(|key| find_option(key).is_some()));
+        assert_eq!(
+            default.get("navigation.dotnet_navigate_to_decompiled_sources"),
+            Some(&json!(true))
+        );
+    }
+
+    #[test]
+    fn test_find_closest() {
+        assert_eq!(
+            find_closest("csharp|inlayhints", known_groups()).as_deref(),
+            Some("csharp|inlay_hints")
+        );
+        assert_eq!(find_closest("completely_unrelated", known_groups()), None);
+    }
+}
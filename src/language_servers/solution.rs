@@ -0,0 +1,339 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use zed_extension_api::{self as zed, serde_json};
+
+use crate::utils;
+
+/// Folders, relative to the project root, that are searched for a solution named after
+/// the project root.
+const SOLUTION_DIRS: &[&str] = &["", "src"];
+/// Solution extensions, most preferred first.
+const SOLUTION_EXTENSIONS: &[&str] = &["slnx", "sln"];
+
+/// Resolves the `solution` setting, a path relative to the project root or absolute, to
+/// an absolute path.
+pub fn resolve_configured_solution(
+    worktree: &zed::Worktree,
+    project_root: &str,
+    solution: &str,
+) -> Result<String, String> {
+    let path = Path::new(project_root).join(solution);
+
+    // Solutions outside of the worktree cannot be read, see crate::utils#file-access, so
+    // they are left for the wrapper to check.
+    if let Some(relative_path) = get_worktree_path(worktree, &path)
+        && worktree.read_text_file(&relative_path).is_err()
+    {
+        return Err(format!("solution {} does not exist", path.display()));
+    }
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Looks for a solution named after the project root, the convention `dotnet new sln`
+/// follows, and returns its absolute path.
+///
+/// The worktree cannot be listed (see [file access](crate::utils#file-access)), so the
+/// wrapper picks a solution when no solution is named after the project root.
+pub fn find_solution(worktree: &zed::Worktree, project_root: &str) -> Option<String> {
+    let relative_root = get_worktree_path(worktree, Path::new(project_root))?;
+    let name = Path::new(project_root)
+        .file_name()?
+        .to_string_lossy()
+        .to_string();
+
+    let (relative_root, name) = (&relative_root, &name);
+
+    SOLUTION_DIRS
+        .iter()
+        .flat_map(|dir| {
+            SOLUTION_EXTENSIONS.iter().map(move |extension| {
+                normalize(&format!("{relative_root}/{dir}/{name}.{extension}"))
+            })
+        })
+        .find(|path| worktree.read_text_file(path).is_ok())
+        .map(|path| {
+            Path::new(&worktree.root_path())
+                .join(path)
+                .to_string_lossy()
+                .to_string()
+        })
+}
+
+/// Returns `path` relative to the worktree root with `/` separators, if it is inside the
+/// worktree.
+fn get_worktree_path(worktree: &zed::Worktree, path: &Path) -> Option<String> {
+    Some(
+        path.strip_prefix(worktree.root_path())
+            .ok()?
+            .to_string_lossy()
+            .replace('\\', "/"),
+    )
+}
+
+/// What the wrapper loads.
+#[derive(Clone)]
+pub enum Workspace {
+    /// A solution, which loads all of its projects.
+    Solution(String),
+    /// A file listing the absolute paths of a subset of the projects of a solution.
+    Projects(String),
+    /// Whatever is in the project root the wrapper finds around the worktree root.
+    Discover,
+}
+
+impl Workspace {
+    /// The roslynls argument that passes the workspace.
+    pub fn argument(&self) -> &'static str {
+        match self {
+            Workspace::Solution(_) => "--solution",
+            Workspace::Projects(_) => "--projects-file",
+            Workspace::Discover => "--discover-project-root",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SolutionFilter {
+    solution: SolutionFilterSolution,
+}
+
+#[derive(Deserialize)]
+struct SolutionFilterSolution {
+    path: String,
+    projects: Vec<String>,
+}
+
+/// Returns the projects of a solution or solution filter inside the worktree, as paths
+/// relative to the worktree root with `/` separators.
+pub fn read_projects(worktree: &zed::Worktree, solution: &str) -> Result<Vec<String>, String> {
+    let relative_path = get_worktree_path(worktree, Path::new(solution)).ok_or_else(|| {
+        format!("cannot read the projects of {solution}, which is outside of the worktree")
+    })?;
+    let text = worktree
+        .read_text_file(&relative_path)
+        .map_err(|e| format!("failed to read {solution}: {e}"))?;
+    let solution_dir = parent_dir(&relative_path);
+
+    let extension = Path::new(&relative_path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    let (solution_dir, projects) = match extension.as_deref() {
+        Some("slnf") => {
+            let filter: SolutionFilter = serde_json::from_str(&text)
+                .map_err(|e| format!("failed to parse {solution}: {e}"))?;
+            // Projects of a filter are relative to the solution it filters.
+            let filtered_solution = normalize(&format!(
+                "{solution_dir}/{}",
+                filter.solution.path.replace('\\', "/")
+            ));
+
+            (parent_dir(&filtered_solution), filter.solution.projects)
+        }
+        Some("slnx") => (solution_dir, parse_slnx_projects(&text)),
+        _ => (solution_dir, parse_sln_projects(&text)),
+    };
+
+    Ok(projects
+        .iter()
+        .map(|project| normalize(&format!("{solution_dir}/{}", project.replace('\\', "/"))))
+        .collect())
+}
+
+/// Writes the absolute paths of `projects` to a JSON file in the extension's work directory
+/// and returns its absolute path. A large subset would not fit on the command line.
+pub fn write_projects_file(worktree_id: u64, projects: &[String]) -> Result<String, String> {
+    let path = format!("projects-{worktree_id}.json");
+    let json = serde_json::to_string(projects).map_err(|e| e.to_string())?;
+
+    std::fs::write(&path, json).map_err(|e| format!("failed to write {path}: {e}"))?;
+
+    Ok(utils::install::get_absolute_path(Path::new(&path)))
+}
+
+/// Keeps the projects that match one of the `include` globs, or all projects when there
+/// are none, and do not match one of the `exclude` globs.
+pub fn filter_projects(
+    projects: Vec<String>,
+    include: &[String],
+    exclude: &[String],
+) -> Vec<String> {
+    projects
+        .into_iter()
+        .filter(|project| {
+            (include.is_empty() || include.iter().any(|glob| glob_match(glob, project)))
+                && !exclude.iter().any(|glob| glob_match(glob, project))
+        })
+        .collect()
+}
+
+/// Project entries of a `.sln`: `Project("{type}") = "Name", "path", "{guid}"`. Solution
+/// folders are entries too, but their path is not a project file.
+fn parse_sln_projects(text: &str) -> Vec<String> {
+    text.lines()
+        .filter(|line| line.trim_start().starts_with("Project("))
+        .filter_map(|line| line.split('"').nth(5))
+        .filter(|path| path.ends_with("proj"))
+        .map(String::from)
+        .collect()
+}
+
+/// Project elements of a `.slnx`: `<Project Path="path" />`.
+fn parse_slnx_projects(text: &str) -> Vec<String> {
+    text.split("<Project ")
+        .skip(1)
+        .filter_map(|element| {
+            let element = &element[..element.find('>').unwrap_or(element.len())];
+            let start = element.find("Path=\"")? + "Path=\"".len();
+            let end = start + element[start..].find('"')?;
+            Some(element[start..end].to_string())
+        })
+        .collect()
+}
+
+fn parent_dir(path: &str) -> String {
+    path.rsplit_once('/')
+        .map(|(dir, _)| dir.to_string())
+        .unwrap_or_default()
+}
+
+/// Resolves `.` and `..` in a relative path with `/` separators.
+fn normalize(path: &str) -> String {
+    let mut segments = Vec::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    segments.join("/")
+}
+
+/// Matches a path relative to the worktree root against a glob, where `**` matches any
+/// number of folders, `*` any part of a name and `?` a single character.
+fn glob_match(glob: &str, path: &str) -> bool {
+    let glob: Vec<&str> = glob
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let path: Vec<&str> = path.split('/').collect();
+
+    match_segments(&glob, &path)
+}
+
+fn match_segments(glob: &[&str], path: &[&str]) -> bool {
+    match glob.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=path.len()).any(|skipped| match_segments(rest, &path[skipped..]))
+        }
+        Some((segment, rest)) => {
+            !path.is_empty()
+                && match_name(
+                    &segment.chars().collect::<Vec<_>>(),
+                    &path[0].chars().collect::<Vec<_>>(),
+                )
+                && match_segments(rest, &path[1..])
+        }
+    }
+}
+
+fn match_name(glob: &[char], name: &[char]) -> bool {
+    match glob.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skipped| match_name(rest, &name[skipped..])),
+        Some(('?', rest)) => !name.is_empty() && match_name(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_name(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("src/./App/../Lib/Lib.csproj"),
+            "src/Lib/Lib.csproj"
+        );
+        assert_eq!(normalize("/src//App.csproj"), "src/App.csproj");
+        assert_eq!(normalize("../App.csproj"), "App.csproj");
+        assert_eq!(normalize(""), "");
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("src/**", "src/App/App.csproj"));
+        assert!(glob_match(
+            "**/*.Tests.csproj",
+            "test/App.Tests/App.Tests.csproj"
+        ));
+        assert!(glob_match("**/*.Tests.csproj", "App.Tests.csproj"));
+        assert!(glob_match("src/*/App?.csproj", "src/App/App1.csproj"));
+        assert!(!glob_match("src/*/App?.csproj", "src/App/App12.csproj"));
+        assert!(!glob_match("src/*.csproj", "src/App/App.csproj"));
+        assert!(!glob_match("test/**", "src/App/App.csproj"));
+    }
+
+    #[test]
+    fn test_filter_projects() {
+        let projects = vec![
+            "src/App/App.csproj".to_string(),
+            "src/Lib/Lib.csproj".to_string(),
+            "test/App.Tests/App.Tests.csproj".to_string(),
+        ];
+
+        assert_eq!(
+            filter_projects(projects.clone(), &[], &["test/**".into()]),
+            ["src/App/App.csproj", "src/Lib/Lib.csproj"]
+        );
+        assert_eq!(
+            filter_projects(projects, &["src/**".into()], &["**/Lib.csproj".into()]),
+            ["src/App/App.csproj"]
+        );
+    }
+
+    #[test]
+    fn test_parse_sln_projects() {
+        let sln = r#"
+Microsoft Visual Studio Solution File, Format Version 12.00
+Project("{FAE04EC0-301F-11D3-BF4B-00C04F79EFBC}") = "App", "src\App\App.csproj", "{11111111-1111-1111-1111-111111111111}"
+EndProject
+Project("{2150E333-8FDC-42A3-9474-1A3956D46DE8}") = "src", "src", "{22222222-2222-2222-2222-222222222222}"
+EndProject
+Project("{9A19103F-16F7-4668-BE54-9A1E7A4F7556}") = "Lib", "src\Lib\Lib.fsproj", "{33333333-3333-3333-3333-333333333333}"
+EndProject
+"#;
+
+        assert_eq!(
+            parse_sln_projects(sln),
+            [r"src\App\App.csproj", r"src\Lib\Lib.fsproj"]
+        );
+    }
+
+    #[test]
+    fn test_parse_slnx_projects() {
+        let slnx = r#"<Solution>
+  <Folder Name="/src/">
+    <Project Path="src/App/App.csproj" />
+    <Project Type="Classic C#" Path="src/Lib/Lib.csproj">
+      <BuildType Project="Release" />
+    </Project>
+  </Folder>
+  <Project Id="00000000-0000-0000-0000-000000000000" />
+</Solution>"#;
+
+        assert_eq!(
+            parse_slnx_projects(slnx),
+            ["src/App/App.csproj", "src/Lib/Lib.csproj"]
+        );
+    }
+}
@@ -0,0 +1,657 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use serde::Deserialize;
+use zed_extension_api::{self as zed, LanguageServerId, serde_json};
+
+use crate::language_servers::Roslyn;
+use crate::tools::model::{
+    DotnetChannelReleases, DotnetReleaseFile, DotnetReleaseSdk, DotnetReleasesIndex,
+};
+use crate::tools::{ManagedTool, PackageSource};
+use crate::utils::{self, manifest::InstallManifest};
+
+const GLOBAL_JSON: &str = "global.json";
+const DOTNET_SDK: &str = "dotnet_sdk";
+pub const INSTALL_DOTNET_SDK_KEY: &str = "install_dotnet_sdk";
+pub const DOTNET_SDK_FEED_KEY: &str = "dotnet_sdk_feed";
+const DEFAULT_DOTNET_SDK_FEED: &str = "https://builds.dotnet.microsoft.com/dotnet";
+const DOTNET_SDK_RUNTIME_IDENTIFIERS: &[&str] = &[
+    "linux-x64",
+    "linux-arm64",
+    "linux-musl-x64",
+    "linux-musl-arm64",
+    "osx-x64",
+    "osx-arm64",
+    "win-x64",
+    "win-x86",
+    "win-arm64",
+];
+
+/// Where .NET SDKs are downloaded from: a server with the layout of
+/// builds.dotnet.microsoft.com, with release metadata under `release-metadata/` and SDKs
+/// under `Sdk/<version>/`.
+pub struct DotnetFeed {
+    pub url: String,
+}
+
+/// The .NET SDK, installed into the extension work directory when the
+/// `install_dotnet_sdk` setting is on.
+pub struct DotnetSdkTool {
+    /// The version global.json asks for after rolling forward, which is installed unless
+    /// the `dotnet_sdk_version` setting pins another.
+    pub required_version: Option<String>,
+}
+
+impl ManagedTool for DotnetSdkTool {
+    fn id(&self) -> &'static str {
+        DOTNET_SDK
+    }
+
+    fn settings_id(&self) -> &'static str {
+        Roslyn::LANGUAGE_SERVER_ID
+    }
+
+    fn package_source(&self, worktree: &zed::Worktree) -> PackageSource {
+        let url = utils::get_setting::<String>(self.settings_id(), worktree, DOTNET_SDK_FEED_KEY)
+            .unwrap_or_else(|| DEFAULT_DOTNET_SDK_FEED.into());
+
+        PackageSource::DotnetRelease(DotnetFeed {
+            url: url.trim_end_matches('/').into(),
+        })
+    }
+
+    fn published_runtime_identifiers(&self) -> &'static [&'static str] {
+        DOTNET_SDK_RUNTIME_IDENTIFIERS
+    }
+
+    fn package_id(&self, runtime_identifier: &str) -> String {
+        format!("dotnet-sdk-{runtime_identifier}")
+    }
+
+    fn executable_name(&self) -> String {
+        utils::get_executable("dotnet")
+    }
+
+    fn required_version(&self) -> Option<String> {
+        self.required_version.clone()
+    }
+
+    fn use_path(&self) -> bool {
+        false
+    }
+}
+
+/// Returns the folder of the managed .NET SDK when the `install_dotnet_sdk` setting is on,
+/// installing a version that satisfies the global.json of `project_root`, or the latest LTS.
+pub fn resolve_sdk(
+    tools: &mut crate::tools::ToolManager,
+    lsp_name: &str,
+    worktree: &zed::Worktree,
+    project_root: &str,
+    language_server_id: Option<&LanguageServerId>,
+) -> Result<Option<String>, String> {
+    if !utils::get_shared_setting::<bool>(lsp_name, worktree, INSTALL_DOTNET_SDK_KEY)
+        .unwrap_or(false)
+    {
+        return Ok(None);
+    }
+
+    let mut tool = DotnetSdkTool {
+        required_version: None,
+    };
+
+    if let Some(pin) = find_global_json(worktree, project_root)
+        .and_then(|(_, global_json)| global_json.sdk)
+        .filter(|pin| pin.version.is_some())
+        && let PackageSource::DotnetRelease(feed) = tool.package_source(worktree)
+    {
+        tool.required_version = Some(select_sdk_version(&feed, &pin));
+    }
+
+    let dotnet_path = tools.resolve(&tool, worktree, language_server_id)?;
+
+    Ok(Path::new(&dotnet_path)
+        .parent()
+        .map(|root| root.to_string_lossy().to_string()))
+}
+
+/// Returns the SDK version to install for a global.json `pin`: the newest SDK of the pinned
+/// version's channel that its `rollForward` policy accepts.
+///
+/// Like the dotnet host, which only rolls forward to installed SDKs, an accepted SDK that is
+/// already installed is kept, unless the policy asks for the latest one.
+fn select_sdk_version(feed: &DotnetFeed, pin: &SdkPin) -> String {
+    let pinned_version = pin.version.clone().unwrap_or_default();
+
+    if pin.roll_forward() == RollForward::Disable {
+        return pinned_version;
+    }
+
+    let manifest = InstallManifest::load();
+    let installed = manifest.tools.get(DOTNET_SDK).and_then(|versions| {
+        pin.pick(versions.iter().map(|installed| installed.version.as_str()))
+            .map(String::from)
+    });
+
+    if let Some(installed) = &installed
+        && !pin.roll_forward().is_latest()
+    {
+        return installed.clone();
+    }
+
+    let published = utils::parse_version(&pinned_version)
+        .ok_or_else(|| format!("invalid .NET SDK version {pinned_version}"))
+        .and_then(|pinned| get_channel_sdks(feed, &pinned))
+        .map(|(_, sdks)| {
+            pin.pick(sdks.iter().map(|sdk| sdk.version.as_str()))
+                .map(String::from)
+        });
+
+    let version = match published {
+        Ok(version) => version.or(installed),
+        Err(e) => {
+            println!("[zed-roslynls] Not rolling the .NET SDK {pinned_version} forward: {e}");
+            installed
+        }
+    }
+    .unwrap_or_else(|| pinned_version.clone());
+
+    if version != pinned_version {
+        println!(
+            "[zed-roslynls] Using .NET SDK {version} for {pinned_version} (rollForward: {})",
+            pin.roll_forward().name()
+        );
+    }
+
+    version
+}
+
+/// Installs the SDK for the first of `runtime_identifiers` that the feed publishes, at
+/// `version` or the latest LTS, and returns the path of its `dotnet` host.
+pub fn ensure_sdk(
+    tool: &impl ManagedTool,
+    feed: &DotnetFeed,
+    runtime_identifiers: &[String],
+    version: Option<&str>,
+    keep_previous_versions: usize,
+    language_server_id: Option<&LanguageServerId>,
+) -> Result<String, String> {
+    let version = match version {
+        Some(version) => version.to_string(),
+        None => get_latest_version(feed)?,
+    };
+
+    for runtime_identifier in runtime_identifiers {
+        let package_id = tool.package_id(runtime_identifier);
+        let version_dir = utils::get_version_dir(package_id.clone(), version.clone());
+
+        if let Some(path) = utils::install::get_installed_executable(tool.id(), &version_dir, None)
+        {
+            return Ok(path);
+        }
+    }
+
+    utils::set_installation_status(
+        language_server_id,
+        zed::LanguageServerInstallationStatus::CheckingForUpdate,
+    );
+
+    let files = get_sdk_files(feed, &version)?;
+
+    for runtime_identifier in runtime_identifiers {
+        let Some(file) = files.iter().find(|file| {
+            file.rid.as_deref() == Some(runtime_identifier.as_str())
+                && (file.name.ends_with(".tar.gz") || file.name.ends_with(".zip"))
+        }) else {
+            continue;
+        };
+
+        // The first RID was already checked when the RIDs were picked.
+        if runtime_identifier != &runtime_identifiers[0] {
+            utils::warn_if_glibc_on_musl(tool.id(), runtime_identifier);
+        }
+
+        utils::set_installation_status(
+            language_server_id,
+            zed::LanguageServerInstallationStatus::Downloading,
+        );
+
+        return install_sdk(
+            tool,
+            feed,
+            &tool.package_id(runtime_identifier),
+            &version,
+            file,
+            keep_previous_versions,
+        );
+    }
+
+    Err(format!(
+        ".NET SDK {version} is not published for {}",
+        runtime_identifiers.join(", ")
+    ))
+}
+
+fn install_sdk(
+    tool: &impl ManagedTool,
+    feed: &DotnetFeed,
+    package_id: &str,
+    version: &str,
+    file: &DotnetReleaseFile,
+    keep_previous_versions: usize,
+) -> Result<String, String> {
+    let version_dir = utils::get_version_dir(package_id.into(), version.into());
+    let extension = if file.name.ends_with(".zip") {
+        "zip"
+    } else {
+        "tar.gz"
+    };
+    // The metadata links to a host that may not be reachable, so the archive is taken
+    // from the feed's `Sdk/<version>/` folder, where it is published under its full name.
+    let url = format!(
+        "{}/Sdk/{version}/dotnet-sdk-{version}-{}.{extension}",
+        feed.url,
+        file.rid.as_deref().unwrap_or_default()
+    );
+
+    println!("[zed-roslynls] Downloading .NET SDK {version} from: {url}");
+
+    let archive_path = utils::install::prepare_staging(&format!("{version_dir}.{extension}"));
+
+    zed::download_file(&url, &archive_path, zed::DownloadedFileType::Uncompressed)
+        .map_err(|e| format!("failed to download .NET SDK {version}: {e}"))?;
+
+    let staging_dir = utils::install::prepare_staging(&version_dir);
+
+    let unpacked = utils::verify::verify_sha512(Path::new(&archive_path), &file.hash)
+        .map_err(|e| format!(".NET SDK {version} failed verification: {e}"))
+        .and_then(|_| match extension {
+            "zip" => utils::zip::extract(Path::new(&archive_path), Path::new(&staging_dir)),
+            _ => utils::tar::extract(Path::new(&archive_path), Path::new(&staging_dir)),
+        });
+
+    fs::remove_file(&archive_path).ok();
+
+    if let Err(e) = unpacked {
+        fs::remove_dir_all(&staging_dir).ok();
+        return Err(e);
+    }
+
+    let executable = tool.executable_name();
+
+    utils::install::make_files_executable(&staging_dir)?;
+    utils::install::commit_install(&staging_dir, &version_dir, &executable)?;
+    utils::manifest::record_install(
+        tool.id(),
+        &format!("{package_id}-"),
+        version,
+        &version_dir,
+        &executable,
+        keep_previous_versions,
+    )?;
+
+    Ok(utils::install::get_absolute_path(
+        &utils::install::get_executable_path(&version_dir, &executable),
+    ))
+}
+
+/// Returns the files published for SDK `version`, from the release metadata of its channel.
+fn get_sdk_files(feed: &DotnetFeed, version: &str) -> Result<Vec<DotnetReleaseFile>, String> {
+    let parsed = utils::parse_version(version)
+        .ok_or_else(|| format!("invalid .NET SDK version {version}"))?;
+    let (url, sdks) = get_channel_sdks(feed, &parsed)?;
+
+    sdks.into_iter()
+        .find(|sdk| sdk.version == version)
+        .map(|sdk| sdk.files)
+        .ok_or_else(|| format!(".NET SDK {version} is not listed in {url}"))
+}
+
+/// Returns the SDKs published in the channel of `version`, like 8.0, and the URL of the
+/// channel's release metadata.
+fn get_channel_sdks(
+    feed: &DotnetFeed,
+    version: &Version,
+) -> Result<(String, Vec<DotnetReleaseSdk>), String> {
+    let url = format!(
+        "{}/release-metadata/{}.{}/releases.json",
+        feed.url, version.major, version.minor
+    );
+
+    let releases: DotnetChannelReleases = serde_json::from_slice(
+        &utils::http_get(&url)
+            .map_err(|e| format!("failed to fetch the .NET release metadata from {url}: {e}"))?,
+    )
+    .map_err(|e| format!("unexpected .NET release metadata from {url}: {e}"))?;
+
+    let sdks = releases
+        .releases
+        .into_iter()
+        .flat_map(|release| release.sdk.into_iter().chain(release.sdks))
+        .collect();
+
+    Ok((url, sdks))
+}
+
+/// Returns the latest SDK of the newest LTS channel that is still supported.
+pub fn get_latest_version(feed: &DotnetFeed) -> Result<String, String> {
+    let url = format!("{}/release-metadata/releases-index.json", feed.url);
+
+    let index: DotnetReleasesIndex = serde_json::from_slice(
+        &utils::http_get(&url)
+            .map_err(|e| format!("failed to fetch the .NET release index from {url}: {e}"))?,
+    )
+    .map_err(|e| format!("unexpected .NET release index from {url}: {e}"))?;
+
+    index
+        .releases_index
+        .into_iter()
+        .filter(|channel| channel.release_type == "lts" && channel.support_phase == "active")
+        .max_by(|a, b| utils::compare_versions(&a.latest_sdk, &b.latest_sdk))
+        .map(|channel| channel.latest_sdk)
+        .ok_or_else(|| format!("no supported LTS release is listed in {url}"))
+}
+
+#[derive(Deserialize)]
+struct GlobalJson {
+    sdk: Option<SdkPin>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SdkPin {
+    version: Option<String>,
+    roll_forward: Option<RollForward>,
+    allow_prerelease: Option<bool>,
+}
+
+impl SdkPin {
+    fn roll_forward(&self) -> RollForward {
+        self.roll_forward.unwrap_or(RollForward::Patch)
+    }
+
+    /// Whether SDK `version` satisfies the pin, following its `rollForward` and
+    /// `allowPrerelease` settings.
+    fn accepts(&self, version: &str) -> bool {
+        let (Some(pinned), Some(sdk)) = (
+            self.version.as_deref().and_then(utils::parse_version),
+            utils::parse_version(version),
+        ) else {
+            return false;
+        };
+
+        (self.allow_prerelease.unwrap_or(true) || sdk.pre.is_empty())
+            && self.roll_forward().accepts(&pinned, &sdk)
+    }
+
+    /// Returns the newest of `versions` that satisfies the pin.
+    fn pick<'a>(&self, versions: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+        versions
+            .into_iter()
+            .filter(|version| self.accepts(version))
+            .max_by(|a, b| utils::compare_versions(a, b))
+    }
+}
+
+/// The `rollForward` policies of global.json. The `latest*` policies pick a different SDK
+/// than their counterparts, but accept the same ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum RollForward {
+    Patch,
+    Feature,
+    Minor,
+    Major,
+    LatestPatch,
+    LatestFeature,
+    LatestMinor,
+    LatestMajor,
+    Disable,
+}
+
+impl RollForward {
+    /// Whether the policy asks for the newest accepted SDK even when an older accepted one
+    /// is installed.
+    fn is_latest(self) -> bool {
+        matches!(
+            self,
+            RollForward::LatestPatch
+                | RollForward::LatestFeature
+                | RollForward::LatestMinor
+                | RollForward::LatestMajor
+        )
+    }
+
+    fn accepts(self, pinned: &Version, sdk: &Version) -> bool {
+        // The hundreds of an SDK's patch number are its feature band.
+        let same_feature_band = sdk.major == pinned.major
+            && sdk.minor == pinned.minor
+            && sdk.patch / 100 == pinned.patch / 100;
+
+        match self {
+            RollForward::Disable => sdk == pinned,
+            RollForward::Patch | RollForward::LatestPatch => same_feature_band && sdk >= pinned,
+            RollForward::Feature | RollForward::LatestFeature => {
+                sdk.major == pinned.major && sdk.minor == pinned.minor && sdk >= pinned
+            }
+            RollForward::Minor | RollForward::LatestMinor => {
+                sdk.major == pinned.major && sdk >= pinned
+            }
+            RollForward::Major | RollForward::LatestMajor => sdk >= pinned,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            RollForward::Patch => "patch",
+            RollForward::Feature => "feature",
+            RollForward::Minor => "minor",
+            RollForward::Major => "major",
+            RollForward::LatestPatch => "latestPatch",
+            RollForward::LatestFeature => "latestFeature",
+            RollForward::LatestMinor => "latestMinor",
+            RollForward::LatestMajor => "latestMajor",
+            RollForward::Disable => "disable",
+        }
+    }
+}
+
+/// Checks that one of `installed_sdks`, as listed by `dotnet --list-sdks`, satisfies the
+/// global.json that applies to `project_root`, since the server cannot load projects
+/// without one.
+pub fn check_sdk(
+    worktree: &zed::Worktree,
+    project_root: &str,
+    installed_sdks: Option<&[(String, PathBuf)]>,
+) -> Result<(), String> {
+    let pin = find_global_json(worktree, project_root).and_then(|(path, global_json)| {
+        let sdk = global_json.sdk?;
+        Some((path, sdk.version.clone()?, sdk))
+    });
+
+    let Some(sdks) = installed_sdks else {
+        if utils::find_dotnet(worktree).is_none() {
+            return Err(format!(
+                "no .NET SDK was found: dotnet is not on PATH{}; install the .NET SDK from https://dotnet.microsoft.com/download",
+                pin.map(|(_, version, _)| format!(" and {version} is required"))
+                    .unwrap_or_default()
+            ));
+        }
+
+        println!("[zed-roslynls] Not checking the .NET SDK: failed to list the installed SDKs");
+        return Ok(());
+    };
+
+    let versions: Vec<&str> = sdks.iter().map(|(version, _)| version.as_str()).collect();
+
+    let Some((path, pinned_version, pin)) = pin else {
+        if versions.is_empty() {
+            return Err(
+                "no .NET SDK is installed; install one from https://dotnet.microsoft.com/download"
+                    .into(),
+            );
+        }
+
+        return Ok(());
+    };
+
+    utils::parse_version(&pinned_version)
+        .ok_or_else(|| format!("{path} pins the invalid .NET SDK version {pinned_version}"))?;
+    let roll_forward = pin.roll_forward();
+
+    let matching = versions.iter().find(|version| pin.accepts(version));
+
+    match matching {
+        Some(version) => {
+            println!(
+                "[zed-roslynls] .NET SDK {version} satisfies {pinned_version} pinned in {path}"
+            );
+            Ok(())
+        }
+        None => Err(format!(
+            "{path} requires .NET SDK {pinned_version} (rollForward: {}), but {}; install a matching SDK or change global.json",
+            roll_forward.name(),
+            if versions.is_empty() {
+                "no SDK is installed".to_string()
+            } else {
+                format!("the installed SDKs are {}", versions.join(", "))
+            }
+        )),
+    }
+}
+
+/// Returns the global.json the dotnet host would use for `project_root`: the first one in
+/// it or its parents, as far as the worktree goes.
+fn find_global_json(worktree: &zed::Worktree, project_root: &str) -> Option<(String, GlobalJson)> {
+    let worktree_root = worktree.root_path();
+    let relative_root = Path::new(project_root).strip_prefix(&worktree_root).ok()?;
+
+    for dir in relative_root.ancestors() {
+        let path = dir.join(GLOBAL_JSON).to_string_lossy().replace('\\', "/");
+
+        if let Ok(text) = worktree.read_text_file(&path) {
+            // The dotnet host accepts comments in global.json, which serde_json does not,
+            // so a file that does not parse is left for the host to check.
+            let global_json = serde_json::from_str(&text)
+                .inspect_err(|e| {
+                    println!(
+                        "[zed-roslynls] Not checking the .NET SDK: failed to parse {path}: {e}"
+                    )
+                })
+                .ok()?;
+
+            return Some((
+                Path::new(&worktree_root)
+                    .join(&path)
+                    .to_string_lossy()
+                    .to_string(),
+                global_json,
+            ));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepts(roll_forward: RollForward, pinned: &str, sdk: &str) -> bool {
+        roll_forward.accepts(
+            &Version::parse(pinned).unwrap(),
+            &Version::parse(sdk).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_patch_stays_in_feature_band() {
+        assert!(accepts(RollForward::Patch, "8.0.100", "8.0.100"));
+        assert!(accepts(RollForward::Patch, "8.0.100", "8.0.199"));
+        assert!(!accepts(RollForward::Patch, "8.0.100", "8.0.200"));
+        assert!(!accepts(RollForward::Patch, "8.0.105", "8.0.104"));
+        assert!(accepts(RollForward::LatestPatch, "8.0.300", "8.0.312"));
+        assert!(!accepts(RollForward::LatestPatch, "8.0.300", "8.0.400"));
+    }
+
+    #[test]
+    fn test_feature_and_wider_policies() {
+        assert!(accepts(RollForward::Feature, "8.0.100", "8.0.404"));
+        assert!(!accepts(RollForward::Feature, "8.0.100", "8.1.100"));
+        assert!(!accepts(RollForward::Feature, "8.0.300", "8.0.299"));
+        assert!(accepts(RollForward::Minor, "8.0.100", "8.1.100"));
+        assert!(!accepts(RollForward::Minor, "8.0.100", "9.0.100"));
+        assert!(accepts(RollForward::LatestMajor, "8.0.100", "10.0.100"));
+        assert!(!accepts(RollForward::LatestMajor, "8.0.100", "7.0.410"));
+    }
+
+    #[test]
+    fn test_disable_needs_exact_version() {
+        assert!(accepts(RollForward::Disable, "8.0.100", "8.0.100"));
+        assert!(!accepts(RollForward::Disable, "8.0.100", "8.0.101"));
+    }
+
+    #[test]
+    fn test_prerelease_versions() {
+        // A release satisfies a pinned prerelease of the same feature band, but a
+        // prerelease does not satisfy the release it precedes.
+        assert!(accepts(
+            RollForward::Patch,
+            "9.0.100-rc.2.24474.11",
+            "9.0.100"
+        ));
+        assert!(accepts(
+            RollForward::Patch,
+            "9.0.100-rc.1.24452.12",
+            "9.0.100-rc.2.24474.11"
+        ));
+        assert!(!accepts(
+            RollForward::Patch,
+            "9.0.100",
+            "9.0.100-rc.2.24474.11"
+        ));
+        assert!(accepts(
+            RollForward::LatestMajor,
+            "9.0.100",
+            "10.0.100-preview.1.25120.13"
+        ));
+    }
+
+    #[test]
+    fn test_pick_newest_accepted_sdk() {
+        let published = [
+            "8.0.100",
+            "8.0.111",
+            "8.0.204",
+            "8.0.405",
+            "8.0.300-rc.1.24255.1",
+        ];
+        let pin = |roll_forward, allow_prerelease| SdkPin {
+            version: Some("8.0.100".into()),
+            roll_forward: Some(roll_forward),
+            allow_prerelease: Some(allow_prerelease),
+        };
+
+        assert_eq!(
+            pin(RollForward::Patch, true).pick(published),
+            Some("8.0.111")
+        );
+        assert_eq!(
+            pin(RollForward::LatestFeature, true).pick(published),
+            Some("8.0.405")
+        );
+        assert_eq!(
+            pin(RollForward::Disable, true).pick(published),
+            Some("8.0.100")
+        );
+
+        let pin = SdkPin {
+            version: Some("8.0.250".into()),
+            roll_forward: Some(RollForward::Feature),
+            allow_prerelease: Some(false),
+        };
+        assert_eq!(pin.pick(["8.0.204", "8.0.300-rc.1.24255.1"]), None);
+    }
+}
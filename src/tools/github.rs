@@ -0,0 +1,468 @@
+use std::path::Path;
+
+use zed_extension_api::{self as zed, LanguageServerId};
+
+use crate::tools::ManagedTool;
+use crate::tools::model::GitHubReleaseInfo;
+use crate::utils::{self, install, manifest};
+
+/// Why a tool could not be installed from a GitHub release.
+#[derive(Debug)]
+pub enum ReleaseError {
+    /// The release has none of the assets for the current runtime identifiers.
+    AssetMissing {
+        repo: String,
+        tag: String,
+        package_ids: Vec<String>,
+    },
+    /// GitHub refused the request because the API rate limit is exhausted.
+    RateLimited { repo: String, message: String },
+    /// GitHub refused the request for another reason, like a private repository or a
+    /// token without access to it.
+    AccessDenied { repo: String, message: String },
+    /// The release could not be fetched.
+    Network {
+        repo: String,
+        tag: String,
+        message: String,
+    },
+    /// The asset could not be downloaded.
+    Download { url: String, message: String },
+    /// The asset was downloaded but could not be installed.
+    Install(String),
+}
+
+impl std::fmt::Display for ReleaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReleaseError::AssetMissing {
+                repo,
+                tag,
+                package_ids,
+            } if package_ids.is_empty() => {
+                write!(f, "{repo} {tag} is not published for this platform")
+            }
+            ReleaseError::AssetMissing {
+                repo,
+                tag,
+                package_ids,
+            } => write!(f, "{repo} {tag} has no {} asset", package_ids.join(" or ")),
+            ReleaseError::RateLimited { repo, message } => write!(
+                f,
+                "the GitHub API rate limit was reached while fetching {repo}, try again later ({message})"
+            ),
+            ReleaseError::AccessDenied { repo, message } => {
+                write!(f, "access to {repo} was denied ({message})")
+            }
+            ReleaseError::Network { repo, tag, message } => {
+                write!(f, "failed to fetch {repo} {tag}: {message}")
+            }
+            ReleaseError::Download { url, message } => {
+                write!(f, "failed to download {url}: {message}")
+            }
+            ReleaseError::Install(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for ReleaseError {
+    fn from(message: String) -> Self {
+        ReleaseError::Install(message)
+    }
+}
+
+pub struct GitHubRelease {
+    pub repo: &'static str,
+    /// The release to install when no newer compatible one is found.
+    pub tag: &'static str,
+    pub file_type: zed::DownloadedFileType,
+    /// The semver range of releases that work with this version of the extension; newer
+    /// releases in the range are installed automatically. Without a range, any newer
+    /// release is.
+    pub compatible_versions: Option<&'static str>,
+}
+
+/// Returns the tag of the highest release of `release.repo` within
+/// `release.compatible_versions`, if set, when it is newer than `release.tag`.
+pub fn get_latest_compatible_tag(release: &GitHubRelease) -> Option<String> {
+    let compatible_versions = match release.compatible_versions {
+        Some(compatible_versions) => Some(semver::VersionReq::parse(compatible_versions).ok()?),
+        None => None,
+    };
+
+    let releases = list_releases(release.repo)
+        .inspect_err(|e| {
+            println!(
+                "[zed-roslynls] Failed to list the {} releases: {e}",
+                release.repo
+            )
+        })
+        .ok()?;
+
+    let Some((tag, version)) = find_latest_compatible(&releases, compatible_versions.as_ref())
+    else {
+        println!(
+            "[zed-roslynls] No {} release is compatible with this extension, which supports {}",
+            release.repo,
+            release.compatible_versions.unwrap_or("any version")
+        );
+        return None;
+    };
+
+    if utils::parse_version(release.tag).is_some_and(|default_version| version <= default_version) {
+        return None;
+    }
+
+    println!(
+        "[zed-roslynls] Using {} {tag} instead of {}",
+        release.repo, release.tag
+    );
+
+    Some(tag.to_string())
+}
+
+/// Lists the most recent releases of `repo`, newest first.
+fn list_releases(repo: &str) -> Result<Vec<GitHubReleaseInfo>, String> {
+    let url = format!("https://api.github.com/repos/{repo}/releases?per_page=100");
+
+    serde_json::from_slice(&utils::http_get(&url)?).map_err(|e| e.to_string())
+}
+
+/// Returns the tag and version of the highest published release with assets that
+/// `compatible_versions` accepts. Releases are not listed in version order, since a fix
+/// for an older line can be published after a newer release.
+fn find_latest_compatible<'a>(
+    releases: &'a [GitHubReleaseInfo],
+    compatible_versions: Option<&semver::VersionReq>,
+) -> Option<(&'a str, semver::Version)> {
+    releases
+        .iter()
+        .filter(|release| !release.draft && !release.prerelease && !release.assets.is_empty())
+        .filter_map(|release| {
+            let version = utils::parse_version(&release.tag_name)?;
+            Some((release.tag_name.as_str(), version))
+        })
+        .filter(|(_, version)| {
+            compatible_versions
+                .is_none_or(|compatible_versions| compatible_versions.matches(version))
+        })
+        .max_by(|(_, a), (_, b)| a.cmp(b))
+}
+
+/// Installs the first of `package_ids`, the release assets in order of preference, that
+/// the release publishes under `tag`, and returns the executable path.
+///
+/// With a `release_url`, assets are downloaded from that server instead of being looked up
+/// through the GitHub API.
+pub fn ensure_release(
+    tool: &impl ManagedTool,
+    release: &GitHubRelease,
+    tag: &str,
+    release_url: Option<&str>,
+    package_ids: &[String],
+    keep_previous_versions: usize,
+    language_server_id: Option<&LanguageServerId>,
+) -> Result<String, ReleaseError> {
+    let package = tool.id();
+    let repo = release.repo;
+
+    for package_id in package_ids {
+        let download_path = utils::get_version_dir(package_id.clone(), tag.into());
+
+        if let Some(executable_path) =
+            install::get_installed_executable(package, &download_path, None)
+        {
+            println!(
+                "[zed-roslynls] {} already downloaded at: {}",
+                package, download_path
+            );
+
+            return Ok(executable_path);
+        }
+    }
+
+    utils::set_installation_status(
+        language_server_id,
+        zed::LanguageServerInstallationStatus::CheckingForUpdate,
+    );
+
+    if let Some(release_url) = release_url {
+        for package_id in package_ids {
+            let download_url = expand_release_url(release_url, repo, tag, package_id);
+
+            match install_asset(
+                tool,
+                release.file_type,
+                package_id,
+                &download_url,
+                tag,
+                keep_previous_versions,
+                language_server_id,
+            ) {
+                // A mirror has no listing of the assets, so a missing one shows up as a
+                // failed download.
+                Err(ReleaseError::Download { message, .. })
+                    if get_http_status(&message) == Some(404) =>
+                {
+                    println!("[zed-roslynls] {download_url} was not found");
+                }
+                result => return result,
+            }
+        }
+
+        return Err(ReleaseError::AssetMissing {
+            repo: repo.into(),
+            tag: tag.into(),
+            package_ids: package_ids.to_vec(),
+        });
+    }
+
+    match zed::github_release_by_tag_name(repo, tag) {
+        Ok(github_release) => {
+            let asset = package_ids.iter().find_map(|package_id| {
+                github_release
+                    .assets
+                    .iter()
+                    .find(|asset| &asset.name == package_id)
+                    .map(|asset| (package_id, asset))
+            });
+
+            println!("[zed-roslynls] Found asset: {:?} {:?}", package_ids, asset);
+
+            let Some((package_id, asset)) = asset else {
+                return Err(ReleaseError::AssetMissing {
+                    repo: repo.into(),
+                    tag: tag.into(),
+                    package_ids: package_ids.to_vec(),
+                });
+            };
+
+            install_asset(
+                tool,
+                release.file_type,
+                package_id,
+                &asset.download_url,
+                tag,
+                keep_previous_versions,
+                language_server_id,
+            )
+        }
+        Err(message) if is_rate_limited(&message) => Err(ReleaseError::RateLimited {
+            repo: repo.into(),
+            message,
+        }),
+        Err(message) if get_http_status(&message) == Some(403) => Err(ReleaseError::AccessDenied {
+            repo: repo.into(),
+            message,
+        }),
+        Err(message) => Err(ReleaseError::Network {
+            repo: repo.into(),
+            tag: tag.into(),
+            message,
+        }),
+    }
+}
+
+/// Downloads the `package_id` asset of `tool` from `download_url` and installs it as
+/// version `tag`.
+fn install_asset(
+    tool: &impl ManagedTool,
+    file_type: zed::DownloadedFileType,
+    package_id: &str,
+    download_url: &str,
+    tag: &str,
+    keep_previous_versions: usize,
+    language_server_id: Option<&LanguageServerId>,
+) -> Result<String, ReleaseError> {
+    let package = tool.id();
+    let download_path = utils::get_version_dir(package_id.into(), tag.into());
+    println!(
+        "[zed-roslynls] Downloading from: {}, to: {}",
+        download_url, download_path
+    );
+
+    utils::set_installation_status(
+        language_server_id,
+        zed::LanguageServerInstallationStatus::Downloading,
+    );
+
+    let staging_path = install::prepare_staging(&download_path);
+
+    zed::download_file(download_url, staging_path.as_str(), file_type).map_err(|message| {
+        manifest::remove_path(&staging_path);
+        ReleaseError::Download {
+            url: download_url.into(),
+            message,
+        }
+    })?;
+
+    let relative_executable = find_executable(tool, &staging_path, file_type)
+        .inspect_err(|_| manifest::remove_path(&staging_path))?;
+
+    zed::make_file_executable(
+        &install::get_executable_path(&staging_path, &relative_executable).to_string_lossy(),
+    )
+    .map_err(|e| format!("Failed to make {package} executable: {e}"))?;
+
+    install::commit_install(&staging_path, &download_path, &relative_executable)?;
+
+    manifest::record_install(
+        package,
+        &format!("{package_id}-"),
+        tag,
+        &download_path,
+        &relative_executable,
+        keep_previous_versions,
+    )?;
+
+    Ok(install::get_absolute_path(&install::get_executable_path(
+        &download_path,
+        &relative_executable,
+    )))
+}
+
+/// Builds the download URL of an asset from the `github_release_url` setting, which is
+/// either a template with `{repo}`, `{tag}` and `{asset}` placeholders or the base URL of
+/// a GitHub Enterprise server or mirror with github.com's URL layout.
+fn expand_release_url(release_url: &str, repo: &str, tag: &str, asset: &str) -> String {
+    if release_url.contains("{asset}") {
+        release_url
+            .replace("{repo}", repo)
+            .replace("{tag}", tag)
+            .replace("{asset}", asset)
+    } else {
+        format!(
+            "{}/{repo}/releases/download/{tag}/{asset}",
+            release_url.trim_end_matches('/')
+        )
+    }
+}
+
+/// GitHub answers requests over the rate limit with 429, or with 403 and a body that says
+/// so; other 403s mean the request is not allowed at all.
+fn is_rate_limited(message: &str) -> bool {
+    message.to_lowercase().contains("rate limit") || get_http_status(message) == Some(429)
+}
+
+/// Zed reports failed requests as text, with the HTTP status after the word "status", as
+/// in `download failed with status 404 Not Found`.
+fn get_http_status(message: &str) -> Option<u16> {
+    let message = message.to_lowercase();
+    let (_, rest) = message.split_once("status")?;
+
+    rest.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(3)
+        .find_map(|word| word.parse::<u16>().ok())
+        .filter(|status| (100..600).contains(status))
+}
+
+/// Returns the path of the tool's executable relative to a downloaded asset.
+///
+/// Archives are searched for the executable, since the layout of the extracted tree can
+/// differ between releases; other assets are the executable itself.
+fn find_executable(
+    tool: &impl ManagedTool,
+    staging_path: &str,
+    file_type: zed::DownloadedFileType,
+) -> Result<String, String> {
+    match file_type {
+        zed::DownloadedFileType::Uncompressed | zed::DownloadedFileType::Gzip => Ok(String::new()),
+        zed::DownloadedFileType::Zip | zed::DownloadedFileType::GzipTar => {
+            let executable_name = tool.executable_name();
+
+            let path =
+                utils::find_file(Path::new(staging_path), &executable_name).ok_or_else(|| {
+                    format!(
+                        "the downloaded {} archive does not contain {executable_name}",
+                        tool.id()
+                    )
+                })?;
+
+            println!(
+                "[zed-roslynls] Found {executable_name} at {}",
+                path.display()
+            );
+
+            Ok(path
+                .strip_prefix(staging_path)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::model::GitHubReleaseAsset;
+
+    fn release(tag: &str, prerelease: bool) -> GitHubReleaseInfo {
+        GitHubReleaseInfo {
+            tag_name: tag.into(),
+            draft: false,
+            prerelease,
+            assets: vec![GitHubReleaseAsset {
+                name: "roslynls-linux-x64".into(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_latest_compatible_release() {
+        let releases = [
+            release("v0.0.4", false),
+            release("v0.1.0", false),
+            release("v0.0.9", false),
+            release("v0.0.10-rc.1", true),
+            release("nightly", false),
+            release("v0.0.3", false),
+        ];
+        let compatible_versions = semver::VersionReq::parse(">=0.0.2, <0.1.0").unwrap();
+
+        assert_eq!(
+            find_latest_compatible(&releases, Some(&compatible_versions)).map(|(tag, _)| tag),
+            Some("v0.0.9")
+        );
+        assert_eq!(
+            find_latest_compatible(&releases, None).map(|(tag, _)| tag),
+            Some("v0.1.0")
+        );
+
+        let incompatible = semver::VersionReq::parse(">=1.0.0").unwrap();
+        assert_eq!(find_latest_compatible(&releases, Some(&incompatible)), None);
+    }
+
+    #[test]
+    fn test_http_status() {
+        assert_eq!(
+            get_http_status("download failed with status 404 Not Found"),
+            Some(404)
+        );
+        assert_eq!(
+            get_http_status("status error 403, body: {\"message\": \"Forbidden\"}"),
+            Some(403)
+        );
+        assert_eq!(
+            get_http_status("https://mirror/v1.404/asset: status 500 Internal Server Error"),
+            Some(500)
+        );
+        assert_eq!(
+            get_http_status("connection reset by peer (404 bytes read)"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        assert!(is_rate_limited("status 429 Too Many Requests"));
+        assert!(is_rate_limited(
+            "status 403 Forbidden: API rate limit exceeded for 127.0.0.1"
+        ));
+        assert!(!is_rate_limited(
+            "status 403 Forbidden: Resource not accessible"
+        ));
+        assert!(!is_rate_limited("status 404 Not Found: v429"));
+    }
+}
@@ -0,0 +1,331 @@
+pub mod dotnet;
+pub mod github;
+mod model;
+pub mod nuget;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+use zed_extension_api::{self as zed, LanguageServerId, settings::LspSettings};
+
+use crate::utils::{self, install, manifest::InstallManifest};
+
+pub use dotnet::DotnetFeed;
+pub use github::GitHubRelease;
+pub use nuget::NuGetFeed;
+
+/// Where the packages of a managed tool are downloaded from.
+pub enum PackageSource {
+    /// One release asset per runtime identifier, named by [`ManagedTool::package_id`].
+    GitHubRelease(GitHubRelease),
+    /// One NuGet package per runtime identifier, named by [`ManagedTool::package_id`].
+    NuGet(NuGetFeed),
+    /// .NET SDK archives described by the .NET release metadata.
+    DotnetRelease(DotnetFeed),
+}
+
+/// What to do when a newer version of a tool than the installed one is available.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpgradePolicy {
+    /// Install the newer version on startup.
+    #[default]
+    Auto,
+    /// Keep the installed version and report the newer one in the log.
+    Notify,
+    /// Keep the installed version without looking for a newer one.
+    Never,
+}
+
+/// A tool the extension can download and keep up to date.
+///
+/// Tools only describe themselves; [`ToolManager`] resolves them all the same way.
+pub trait ManagedTool {
+    /// Name of the tool in logs, errors, setting keys and the install manifest.
+    fn id(&self) -> &'static str;
+
+    /// The `lsp.<settings_id>` section the tool's settings are read from.
+    fn settings_id(&self) -> &'static str;
+
+    fn package_source(&self, worktree: &zed::Worktree) -> PackageSource;
+
+    /// The runtime identifiers the tool is published for.
+    fn published_runtime_identifiers(&self) -> &'static [&'static str];
+
+    /// The release asset or package that holds the build for `runtime_identifier`.
+    fn package_id(&self, runtime_identifier: &str) -> String;
+
+    /// The file name of the executable, as looked up on PATH.
+    fn executable_name(&self) -> String;
+
+    /// Path of the executable inside a NuGet package. Release assets are searched for
+    /// the executable instead.
+    fn relative_executable(&self, _runtime_identifier: &str) -> String {
+        self.executable_name()
+    }
+
+    /// The version the project needs, installed when no version is pinned in the settings.
+    fn required_version(&self) -> Option<String> {
+        None
+    }
+
+    /// The runtime identifiers to try, most preferred first.
+    fn runtime_identifiers(&self, worktree: &zed::Worktree) -> Result<Vec<String>, String> {
+        utils::get_tool_runtime_identifiers(
+            self.id(),
+            self.settings_id(),
+            worktree,
+            self.published_runtime_identifiers(),
+        )
+    }
+
+    /// Whether a copy of the tool on PATH may be used.
+    fn use_path(&self) -> bool {
+        true
+    }
+
+    /// The upgrade policy when the `<id>_upgrade_policy` setting is not set.
+    fn default_upgrade_policy(&self) -> UpgradePolicy {
+        UpgradePolicy::Auto
+    }
+
+    /// Looks for an install of the tool that the extension does not manage.
+    fn find_existing(&self, _worktree: &zed::Worktree) -> Option<String> {
+        None
+    }
+}
+
+/// Resolves managed tools through the same chain: the configured path, the cached path,
+/// PATH, existing installs and finally a managed download.
+#[derive(Default)]
+pub struct ToolManager {
+    /// Keyed by the tool, its executable and the version the project needs, since
+    /// worktrees may need different versions of the same tool.
+    cached_paths: HashMap<(&'static str, String, Option<String>), String>,
+}
+
+impl ToolManager {
+    pub fn resolve(
+        &mut self,
+        tool: &impl ManagedTool,
+        worktree: &zed::Worktree,
+        language_server_id: Option<&LanguageServerId>,
+    ) -> Result<String, String> {
+        if let Some(path) = get_configured_path(tool, worktree) {
+            println!("[zed-roslynls] Using configured {}: {path}", tool.id());
+            return Ok(path);
+        }
+
+        let key = (tool.id(), tool.executable_name(), tool.required_version());
+
+        if let Some(path) = self
+            .cached_paths
+            .get(&key)
+            .filter(|path| Path::new(path).is_file())
+        {
+            return Ok(path.clone());
+        }
+
+        if tool.use_path()
+            && let Some(path) = worktree.which(&key.1)
+        {
+            println!("[zed-roslynls] Using {} from PATH: {path}", tool.id());
+            return Ok(path);
+        }
+
+        let path = match tool.find_existing(worktree) {
+            Some(path) => path,
+            None => install(tool, worktree, language_server_id)?,
+        };
+
+        self.cached_paths.insert(key, path.clone());
+
+        Ok(path)
+    }
+}
+
+/// The `<id>_path` setting, or `binary.path` for the tool the settings section belongs to.
+fn get_configured_path(tool: &impl ManagedTool, worktree: &zed::Worktree) -> Option<String> {
+    let binary_path = (tool.id() == tool.settings_id())
+        .then(|| LspSettings::for_worktree(tool.settings_id(), worktree).ok())
+        .flatten()
+        .and_then(|lsp_settings| lsp_settings.binary)
+        .and_then(|binary_settings| binary_settings.path);
+
+    binary_path.or_else(|| {
+        utils::get_setting(tool.settings_id(), worktree, &format!("{}_path", tool.id()))
+    })
+}
+
+/// Installs the tool, at the version pinned by the `<id>_version` setting if there is
+/// one, and returns the executable path.
+///
+/// Without a pinned version, the `<id>_upgrade_policy` setting decides whether an
+/// installed version is kept when a newer one is available.
+fn install(
+    tool: &impl ManagedTool,
+    worktree: &zed::Worktree,
+    language_server_id: Option<&LanguageServerId>,
+) -> Result<String, String> {
+    let runtime_identifiers = tool.runtime_identifiers(worktree)?;
+    let package_ids: Vec<String> = runtime_identifiers
+        .iter()
+        .map(|runtime_identifier| tool.package_id(runtime_identifier))
+        .collect();
+    let pinned_version = utils::get_setting::<String>(
+        tool.settings_id(),
+        worktree,
+        &format!("{}_version", tool.id()),
+    )
+    .or_else(|| tool.required_version());
+    let upgrade_policy = utils::get_setting::<UpgradePolicy>(
+        tool.settings_id(),
+        worktree,
+        &format!("{}_upgrade_policy", tool.id()),
+    )
+    .unwrap_or_else(|| tool.default_upgrade_policy());
+    let keep_previous_versions = utils::get_keep_previous_versions(tool.settings_id(), worktree);
+    let release_url = utils::get_shared_setting::<String>(
+        tool.settings_id(),
+        worktree,
+        utils::GITHUB_RELEASE_URL_KEY,
+    );
+
+    let source = tool.package_source(worktree);
+
+    if pinned_version.is_none()
+        && upgrade_policy != UpgradePolicy::Auto
+        && let Some((version, path)) = find_newest_install(tool, &source, &runtime_identifiers)
+    {
+        if upgrade_policy == UpgradePolicy::Notify
+            && let Some(latest_version) =
+                get_latest_version(&source, &package_ids, release_url.is_some())
+            && utils::compare_versions(&latest_version, &version).is_gt()
+        {
+            println!(
+                "[zed-roslynls] {} {latest_version} is available, keeping {version}; set {}_upgrade_policy to \"auto\" to install it",
+                tool.id(),
+                tool.id()
+            );
+        }
+
+        return Ok(path);
+    }
+
+    match source {
+        PackageSource::GitHubRelease(release) => {
+            // Mirrors have no API to list releases, so they always serve the default tag.
+            let tag = pinned_version
+                .or_else(|| {
+                    release_url
+                        .is_none()
+                        .then(|| github::get_latest_compatible_tag(&release))
+                        .flatten()
+                })
+                .unwrap_or_else(|| release.tag.into());
+
+            github::ensure_release(
+                tool,
+                &release,
+                &tag,
+                release_url.as_deref(),
+                &package_ids,
+                keep_previous_versions,
+                language_server_id,
+            )
+            .or_else(|e| {
+                let allow_path_fallback = utils::get_shared_setting::<bool>(
+                    tool.settings_id(),
+                    worktree,
+                    utils::ALLOW_PATH_FALLBACK_KEY,
+                )
+                .unwrap_or(false);
+
+                if !allow_path_fallback {
+                    return Err(e.to_string());
+                }
+
+                // The executable was not on the worktree's PATH either, but it may still
+                // be found from Zed's environment.
+                println!(
+                    "[zed-roslynls] {e}, falling back to {} on PATH",
+                    tool.executable_name()
+                );
+
+                Ok(tool.executable_name())
+            })
+        }
+        PackageSource::NuGet(feed) => nuget::ensure_package(
+            tool,
+            &feed,
+            &runtime_identifiers,
+            pinned_version.as_deref(),
+            keep_previous_versions,
+            language_server_id,
+        ),
+        PackageSource::DotnetRelease(feed) => dotnet::ensure_sdk(
+            tool,
+            &feed,
+            &runtime_identifiers,
+            pinned_version.as_deref(),
+            keep_previous_versions,
+            language_server_id,
+        ),
+    }
+}
+
+/// Returns the version and executable path of the newest managed install of `tool` for
+/// one of `runtime_identifiers`.
+///
+/// NuGet packages must hold the executable the tool currently asks for, which depends on
+/// settings like the host mode; release assets are searched for theirs when installed.
+fn find_newest_install(
+    tool: &impl ManagedTool,
+    source: &PackageSource,
+    runtime_identifiers: &[String],
+) -> Option<(String, String)> {
+    let packages: Vec<(String, Option<String>)> = runtime_identifiers
+        .iter()
+        .map(|runtime_identifier| {
+            (
+                tool.package_id(runtime_identifier),
+                matches!(source, PackageSource::NuGet(_))
+                    .then(|| tool.relative_executable(runtime_identifier)),
+            )
+        })
+        .collect();
+
+    InstallManifest::load()
+        .tools
+        .get(tool.id())?
+        .iter()
+        .filter_map(|installed| {
+            let (_, executable) = packages
+                .iter()
+                .find(|(package_id, _)| installed.path.starts_with(&format!("{package_id}-")))?;
+            let path = install::get_installed_executable(
+                tool.id(),
+                &installed.path,
+                executable.as_deref(),
+            )?;
+            Some((installed.version.clone(), path))
+        })
+        .max_by(|(a, _), (b, _)| utils::compare_versions(a, b))
+}
+
+/// Returns the newest version `source` offers for the first of `package_ids` it has.
+fn get_latest_version(
+    source: &PackageSource,
+    package_ids: &[String],
+    mirrored: bool,
+) -> Option<String> {
+    match source {
+        PackageSource::GitHubRelease(_) if mirrored => None,
+        PackageSource::GitHubRelease(release) => github::get_latest_compatible_tag(release),
+        PackageSource::NuGet(feed) => package_ids
+            .iter()
+            .find_map(|package_id| nuget::get_latest_version(feed, package_id).ok().flatten()),
+        PackageSource::DotnetRelease(feed) => dotnet::get_latest_version(feed).ok(),
+    }
+}
@@ -75,3 +75,97 @@ pub struct NuGetPackageVersion {
     #[serde(rename = "publishDate")]
     pub publish_date: String,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NuGetRegistrationIndex {
+    pub items: Vec<NuGetRegistrationPage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NuGetRegistrationPage {
+    #[serde(rename = "@id")]
+    pub id: String,
+    pub items: Option<Vec<NuGetRegistrationLeaf>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NuGetRegistrationLeaf {
+    #[serde(rename = "catalogEntry")]
+    pub catalog_entry: NuGetCatalogEntry,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NuGetCatalogEntry {
+    #[serde(rename = "@id")]
+    pub id: String,
+    pub version: String,
+    #[serde(rename = "packageHash")]
+    pub package_hash: Option<String>,
+    #[serde(rename = "packageHashAlgorithm")]
+    pub package_hash_algorithm: Option<String>,
+}
+
+// .NET release metadata, from `<feed>/release-metadata/releases-index.json` and
+// `<feed>/release-metadata/<channel>/releases.json`.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DotnetReleasesIndex {
+    #[serde(rename = "releases-index")]
+    pub releases_index: Vec<DotnetChannel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DotnetChannel {
+    #[serde(rename = "channel-version")]
+    pub channel_version: String,
+    #[serde(rename = "latest-sdk")]
+    pub latest_sdk: String,
+    #[serde(rename = "release-type")]
+    pub release_type: String,
+    #[serde(rename = "support-phase")]
+    pub support_phase: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DotnetChannelReleases {
+    pub releases: Vec<DotnetRelease>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DotnetRelease {
+    pub sdk: Option<DotnetReleaseSdk>,
+    #[serde(default)]
+    pub sdks: Vec<DotnetReleaseSdk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DotnetReleaseSdk {
+    pub version: String,
+    pub files: Vec<DotnetReleaseFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DotnetReleaseFile {
+    pub name: String,
+    pub rid: Option<String>,
+    pub url: String,
+    pub hash: String,
+}
+
+// GitHub releases, from `https://api.github.com/repos/<repo>/releases`.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubReleaseInfo {
+    pub tag_name: String,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub assets: Vec<GitHubReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubReleaseAsset {
+    pub name: String,
+}
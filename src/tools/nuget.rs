@@ -0,0 +1,433 @@
+use std::fs;
+use std::path::Path;
+
+use zed_extension_api::{self as zed, LanguageServerId};
+
+use crate::tools::ManagedTool;
+use crate::tools::model::{
+    NuGetCatalogEntry, NuGetPackage, NuGetPackagesResponse, NuGetRegistrationIndex,
+    NuGetRegistrationPage,
+};
+use crate::utils;
+
+pub enum NuGetFeed {
+    /// A feed hosted in Azure Artifacts.
+    AzureArtifacts {
+        organization: &'static str,
+        project: &'static str,
+        feed: &'static str,
+    },
+    /// A folder of `.nupkg` files, optionally with a `<name>.nupkg.sha512` file next to
+    /// each package.
+    Local(String),
+}
+
+impl NuGetFeed {
+    fn name(&self) -> &str {
+        match self {
+            NuGetFeed::AzureArtifacts { feed, .. } => feed,
+            NuGetFeed::Local(path) => path,
+        }
+    }
+}
+
+/// A version of a managed tool's package in a NuGet feed.
+struct Package {
+    tool: &'static str,
+    package_id: String,
+    version: String,
+    relative_executable: String,
+}
+
+impl Package {
+    fn version_dir(&self) -> String {
+        utils::get_version_dir(self.package_id.clone(), self.version.clone())
+    }
+
+    fn binary_path(&self) -> String {
+        utils::install::get_absolute_path(&utils::install::get_executable_path(
+            &self.version_dir(),
+            &self.relative_executable,
+        ))
+    }
+}
+
+/// Installs the package of `tool` for the first of `runtime_identifiers` that `feed`
+/// has, either at `version` or at the latest version, and returns the executable path.
+pub fn ensure_package(
+    tool: &impl ManagedTool,
+    feed: &NuGetFeed,
+    runtime_identifiers: &[String],
+    version: Option<&str>,
+    keep_previous_versions: usize,
+    language_server_id: Option<&LanguageServerId>,
+) -> Result<String, String> {
+    utils::set_installation_status(
+        language_server_id,
+        zed::LanguageServerInstallationStatus::CheckingForUpdate,
+    );
+
+    let mut errors = Vec::new();
+
+    for runtime_identifier in runtime_identifiers {
+        let package_id = tool.package_id(runtime_identifier);
+
+        let found = match feed {
+            NuGetFeed::AzureArtifacts { .. } => match version {
+                Some(version) => {
+                    let version_dir = utils::get_version_dir(package_id.clone(), version.into());
+                    let installed = utils::install::get_installed_executable(
+                        tool.id(),
+                        &version_dir,
+                        Some(&tool.relative_executable(runtime_identifier)),
+                    )
+                    .is_some();
+
+                    // A pinned version is not necessarily published for every RID.
+                    (installed || has_version(feed, &package_id, version)?)
+                        .then(|| (version.to_string(), None))
+                }
+                None => get_latest_version(feed, &package_id)?.map(|version| (version, None)),
+            },
+            NuGetFeed::Local(local_feed) => {
+                match utils::find_local_nuget_package(local_feed, &package_id, version) {
+                    Ok((version, package_path)) => Some((version, Some(package_path))),
+                    Err(e) => {
+                        errors.push(e);
+                        None
+                    }
+                }
+            }
+        };
+
+        let Some((version, package_path)) = found else {
+            println!(
+                "[zed-roslynls] The {} feed has no {package_id} package{}",
+                feed.name(),
+                version
+                    .map(|version| format!(" at version {version}"))
+                    .unwrap_or_default()
+            );
+            continue;
+        };
+
+        // The first RID was already checked when the RIDs were picked.
+        if runtime_identifier != &runtime_identifiers[0] {
+            utils::warn_if_glibc_on_musl(tool.id(), runtime_identifier);
+        }
+
+        let package = Package {
+            tool: tool.id(),
+            package_id,
+            version,
+            relative_executable: tool.relative_executable(runtime_identifier),
+        };
+
+        if let Some(path) = utils::install::get_installed_executable(
+            package.tool,
+            &package.version_dir(),
+            Some(&package.relative_executable),
+        ) {
+            return Ok(path);
+        }
+
+        utils::set_installation_status(
+            language_server_id,
+            zed::LanguageServerInstallationStatus::Downloading,
+        );
+
+        match package_path {
+            Some(package_path) => install_local_package(&package, &package_path)?,
+            None => download_package(feed, &package)?,
+        }
+
+        finish_install(&package, keep_previous_versions)?;
+
+        return Ok(package.binary_path());
+    }
+
+    if errors.is_empty() {
+        Err(format!(
+            "the {} feed has no {} package for {}",
+            feed.name(),
+            tool.id(),
+            runtime_identifiers.join(", ")
+        ))
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+fn install_local_package(package: &Package, package_path: &Path) -> Result<(), String> {
+    let package_id = &package.package_id;
+    let version = &package.version;
+
+    println!(
+        "[zed-roslynls] Installing {package_id} {version} from local feed: {}",
+        package_path.display()
+    );
+
+    let hash_path = package_path.with_file_name(format!(
+        "{}.sha512",
+        package_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    ));
+
+    match fs::read_to_string(&hash_path) {
+        Ok(expected_hash) => utils::verify::verify_sha512(package_path, &expected_hash)
+            .map_err(|e| format!("package {package_id} {version} failed verification: {e}"))?,
+        Err(_) => println!(
+            "[zed-roslynls] No {} found, skipping hash verification of the local package",
+            hash_path.display()
+        ),
+    }
+
+    unpack_package(package_path, package, false)
+}
+
+fn download_package(feed: &NuGetFeed, package: &Package) -> Result<(), String> {
+    let NuGetFeed::AzureArtifacts {
+        organization,
+        project,
+        feed,
+    } = feed
+    else {
+        return Err("only Azure Artifacts feeds can be downloaded from".into());
+    };
+
+    let package_id = &package.package_id;
+    let version = &package.version;
+
+    let asset_name = utils::get_nuget_asset_name(package_id.clone(), version.clone());
+
+    let url = format!(
+        "https://pkgs.dev.azure.com/{organization}/{project}/_packaging/{feed}/nuget/v3/flat2/{package_id}/{version}/{asset_name}"
+    );
+
+    println!("[zed-roslynls] Downloading {package_id} from: {url}");
+
+    let package_path = utils::install::prepare_staging(&format!("{}.nupkg", package.version_dir()));
+
+    zed::download_file(&url, &package_path, zed::DownloadedFileType::Uncompressed)
+        .map_err(|e| format!("failed to download {package_id} {version}: {e}"))?;
+
+    let verified = get_package_hash(organization, project, feed, package_id, version).and_then(
+        |expected_hash| utils::verify::verify_sha512(Path::new(&package_path), &expected_hash),
+    );
+
+    if let Err(e) = verified {
+        fs::remove_file(&package_path).ok();
+        return Err(format!(
+            "package {package_id} {version} failed verification: {e}"
+        ));
+    }
+
+    let unpacked = unpack_package(Path::new(&package_path), package, true);
+
+    fs::remove_file(&package_path).ok();
+
+    unpacked
+}
+
+/// Extracts the package into the staging location of its version directory.
+///
+/// Packages from Azure Artifacts feeds come from Microsoft's build and must be signed;
+/// packages in a local feed may have been built locally.
+fn unpack_package(
+    package_path: &Path,
+    package: &Package,
+    require_signature: bool,
+) -> Result<(), String> {
+    let package_id = &package.package_id;
+    let version = &package.version;
+    let staging_dir = utils::install::prepare_staging(&package.version_dir());
+
+    utils::zip::extract(package_path, Path::new(&staging_dir))
+        .and_then(|_| {
+            utils::verify::verify_nuget_package(
+                Path::new(&staging_dir),
+                package_id,
+                version,
+                require_signature,
+            )
+        })
+        .map_err(|e| {
+            fs::remove_dir_all(&staging_dir).ok();
+            format!("package {package_id} {version} failed verification: {e}")
+        })
+}
+
+/// Looks up the SHA-512 hash the feed publishes for a package, first in the v3
+/// registration catalog entry and then in the v2 OData `PackageHash` property.
+fn get_package_hash(
+    organization: &str,
+    project: &str,
+    feed: &str,
+    package_id: &str,
+    version: &str,
+) -> Result<String, String> {
+    let registration_hash =
+        get_registration_package_hash(organization, project, feed, package_id, version);
+
+    if let Ok(Some(hash)) = registration_hash {
+        return Ok(hash);
+    }
+
+    let url = format!(
+        "https://pkgs.dev.azure.com/{organization}/{project}/_packaging/{feed}/nuget/v2/Packages(Id='{package_id}',Version='{version}')"
+    );
+
+    let body = utils::http_get(&url)?;
+    let body = String::from_utf8_lossy(&body);
+
+    let algorithm = utils::verify::get_xml_element(&body, "PackageHashAlgorithm");
+    if algorithm.is_some_and(|algorithm| !algorithm.eq_ignore_ascii_case("SHA512")) {
+        return Err("the feed does not publish a SHA-512 hash".into());
+    }
+
+    utils::verify::get_xml_element(&body, "PackageHash")
+        .filter(|hash| !hash.is_empty())
+        .ok_or_else(|| match registration_hash {
+            Err(e) => format!("the feed does not publish a package hash ({e})"),
+            Ok(_) => "the feed does not publish a package hash".into(),
+        })
+}
+
+fn get_registration_package_hash(
+    organization: &str,
+    project: &str,
+    feed: &str,
+    package_id: &str,
+    version: &str,
+) -> Result<Option<String>, String> {
+    let url = format!(
+        "https://pkgs.dev.azure.com/{organization}/{project}/_packaging/{feed}/nuget/v3/registrations2-semver2/{}/index.json",
+        package_id.to_lowercase()
+    );
+
+    let index: NuGetRegistrationIndex =
+        serde_json::from_slice(&utils::http_get(&url)?).map_err(|e| e.to_string())?;
+
+    for page in index.items {
+        let leaves = match page.items {
+            Some(leaves) => leaves,
+            None => {
+                let page: NuGetRegistrationPage =
+                    serde_json::from_slice(&utils::http_get(&page.id)?)
+                        .map_err(|e| e.to_string())?;
+                page.items.unwrap_or_default()
+            }
+        };
+
+        let Some(entry) = leaves
+            .into_iter()
+            .map(|leaf| leaf.catalog_entry)
+            .find(|entry| entry.version.eq_ignore_ascii_case(version))
+        else {
+            continue;
+        };
+
+        let entry = if entry.package_hash.is_some() {
+            entry
+        } else {
+            serde_json::from_slice::<NuGetCatalogEntry>(&utils::http_get(&entry.id)?)
+                .map_err(|e| e.to_string())?
+        };
+
+        return Ok(entry.package_hash.filter(|_| {
+            entry
+                .package_hash_algorithm
+                .as_ref()
+                .is_none_or(|algorithm| algorithm.eq_ignore_ascii_case("SHA512"))
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Makes the unpacked files executable and moves the staged install into place.
+fn finish_install(package: &Package, keep_previous_versions: usize) -> Result<(), String> {
+    let version_dir = package.version_dir();
+    let staging_dir = utils::install::get_staging_path(&version_dir);
+
+    utils::install::make_files_executable(&staging_dir)?;
+
+    utils::install::commit_install(&staging_dir, &version_dir, &package.relative_executable)?;
+
+    utils::manifest::record_install(
+        package.tool,
+        &format!("{}-", package.package_id),
+        &package.version,
+        &version_dir,
+        &package.relative_executable,
+        keep_previous_versions,
+    )
+}
+
+/// Returns the latest version of `package_id` in `feed`, or `None` when the feed has no
+/// such package.
+pub fn get_latest_version(feed: &NuGetFeed, package_id: &str) -> Result<Option<String>, String> {
+    let NuGetFeed::AzureArtifacts { .. } = feed else {
+        return Ok(
+            utils::find_local_nuget_package(feed.name(), package_id, None)
+                .ok()
+                .map(|(version, _)| version),
+        );
+    };
+
+    let Some(package) = query_package(feed, package_id)? else {
+        return Ok(None);
+    };
+
+    // The feed's `isLatest` follows the publish date, so order the versions by SemVer.
+    let version = package
+        .versions
+        .iter()
+        .filter(|v| v.is_listed)
+        .max_by(|a, b| utils::compare_versions(&a.version, &b.version))
+        .or_else(|| package.versions.iter().find(|v| v.is_latest))
+        .ok_or_else(|| format!("no latest version of {package_id} found in the feed"))?;
+
+    Ok(Some(version.version.clone()))
+}
+
+/// Whether `feed` has `version` of `package_id`.
+fn has_version(feed: &NuGetFeed, package_id: &str, version: &str) -> Result<bool, String> {
+    Ok(query_package(feed, package_id)?.is_some_and(|package| {
+        package
+            .versions
+            .iter()
+            .any(|v| v.version.eq_ignore_ascii_case(version))
+    }))
+}
+
+/// Returns the entry of `package_id` in an Azure Artifacts feed, with all of its versions.
+fn query_package(feed: &NuGetFeed, package_id: &str) -> Result<Option<NuGetPackage>, String> {
+    let NuGetFeed::AzureArtifacts {
+        organization,
+        project,
+        feed,
+    } = feed
+    else {
+        return Err("only Azure Artifacts feeds can be queried".into());
+    };
+
+    let url = format!(
+        "https://feeds.dev.azure.com/{organization}/{project}/_apis/packaging/feeds/{feed}/packages?packageNameQuery={package_id}&includeAllVersions=true&api-version=6.0-preview.1",
+    );
+
+    println!("[zed-roslynls] Fetching {package_id} versions from: {url}");
+
+    let nuget_packages: NuGetPackagesResponse = serde_json::from_slice(
+        &utils::http_get(&url)
+            .map_err(|e| format!("failed to query the {feed} feed for {package_id}: {e}"))?,
+    )
+    .map_err(|e| format!("unexpected response from the {feed} feed: {e}"))?;
+
+    Ok(nuget_packages
+        .value
+        .into_iter()
+        .find(|p| p.name.eq_ignore_ascii_case(package_id)))
+}
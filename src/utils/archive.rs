@@ -0,0 +1,167 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A link entry of an archive. Extensions cannot create links, so each link is extracted
+/// as a copy of its target once the rest of the archive is in place.
+pub struct Link {
+    /// Path of the link, relative to the destination.
+    pub path: PathBuf,
+    /// Path of the target, relative to the destination.
+    pub target: PathBuf,
+}
+
+/// Returns the path of an entry relative to the destination, refusing absolute paths and
+/// `..`, which could write outside of it.
+pub fn get_entry_path(name: &Path) -> Result<PathBuf, String> {
+    let mut path = PathBuf::new();
+
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(format!(
+                    "refusing to extract unsafe archive entry: {}",
+                    name.display()
+                ));
+            }
+        }
+    }
+
+    Ok(path)
+}
+
+/// Resolves the target of the link at `path`, relative to the folder of the link for a
+/// symbolic link or to the archive root for a hard link. Targets outside of the archive
+/// and links into themselves are refused.
+pub fn resolve_link(path: &Path, target: &Path, symbolic: bool) -> Result<Link, String> {
+    let mut resolved = match path.parent() {
+        Some(parent) if symbolic => parent.to_path_buf(),
+        _ => PathBuf::new(),
+    };
+
+    for component in target.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            Component::ParentDir if resolved.pop() => {}
+            _ => {
+                return Err(format!(
+                    "refusing to extract {}, a link to {} outside of the archive",
+                    path.display(),
+                    target.display()
+                ));
+            }
+        }
+    }
+
+    if path.starts_with(&resolved) {
+        return Err(format!(
+            "refusing to extract {}, a link to its own folder {}",
+            path.display(),
+            target.display()
+        ));
+    }
+
+    Ok(Link {
+        path: path.to_path_buf(),
+        target: resolved,
+    })
+}
+
+/// Copies the target of each link to the path of the link. Links to other links are
+/// copied once their target is in place.
+pub fn copy_links(destination: &Path, mut links: Vec<Link>) -> Result<(), String> {
+    while !links.is_empty() {
+        let count = links.len();
+        let mut pending = Vec::new();
+
+        for link in links {
+            let target = destination.join(&link.target);
+
+            if target.exists() {
+                copy_path(&target, &destination.join(&link.path))?;
+            } else {
+                pending.push(link);
+            }
+        }
+
+        if let Some(link) = pending.first()
+            && pending.len() == count
+        {
+            return Err(format!(
+                "link {} points to {}, which is not in the archive",
+                link.path.display(),
+                link.target.display()
+            ));
+        }
+
+        links = pending;
+    }
+
+    Ok(())
+}
+
+fn copy_path(source: &Path, target: &Path) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+
+    if !source.is_dir() {
+        return fs::copy(source, target).map(|_| ()).map_err(|e| {
+            format!(
+                "failed to copy {} to {}: {e}",
+                source.display(),
+                target.display()
+            )
+        });
+    }
+
+    fs::create_dir_all(target)
+        .map_err(|e| format!("failed to create {}: {e}", target.display()))?;
+
+    for entry in
+        fs::read_dir(source).map_err(|e| format!("failed to list {}: {e}", source.display()))?
+    {
+        let entry = entry.map_err(|e| format!("failed to list {}: {e}", source.display()))?;
+        copy_path(&entry.path(), &target.join(entry.file_name()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_paths() {
+        assert_eq!(
+            get_entry_path(Path::new("./content/server.dll")),
+            Ok(PathBuf::from("content/server.dll"))
+        );
+        assert!(get_entry_path(Path::new("../evil")).is_err());
+        assert!(get_entry_path(Path::new("content/../../evil")).is_err());
+        assert!(get_entry_path(Path::new("/etc/passwd")).is_err());
+    }
+
+    #[test]
+    fn test_link_targets() {
+        let link = resolve_link(Path::new("bin/dotnet"), Path::new("../host/dotnet"), true);
+        assert_eq!(
+            link.map(|link| link.target),
+            Ok(PathBuf::from("host/dotnet"))
+        );
+
+        let link = resolve_link(Path::new("bin/dotnet"), Path::new("host/dotnet"), false);
+        assert_eq!(
+            link.map(|link| link.target),
+            Ok(PathBuf::from("host/dotnet"))
+        );
+
+        assert!(resolve_link(Path::new("bin/dotnet"), Path::new("../../dotnet"), true).is_err());
+        assert!(resolve_link(Path::new("bin/dotnet"), Path::new("/usr/bin/dotnet"), true).is_err());
+        assert!(resolve_link(Path::new("bin/loop"), Path::new("."), true).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use zed_extension_api as zed;
+
+use crate::utils::manifest::{self, InstallManifest};
+
+const STAGING_PREFIX: &str = ".staging-";
+
+/// Returns an empty staging location for an install that will end up at `path`.
+///
+/// Downloads and unpacking happen in the staging location, so an interrupted install
+/// never leaves a half-populated `path` behind.
+pub fn prepare_staging(path: &str) -> String {
+    let staging_path = get_staging_path(path);
+    manifest::remove_path(&staging_path);
+    staging_path
+}
+
+pub fn get_staging_path(path: &str) -> String {
+    format!("{STAGING_PREFIX}{path}")
+}
+
+/// Moves a staged install into place once `executable` (relative to the install root,
+/// or empty when the install is the executable itself) is present.
+pub fn commit_install(staging_path: &str, path: &str, executable: &str) -> Result<(), String> {
+    let staged_executable = get_executable_path(staging_path, executable);
+
+    if !staged_executable.is_file() {
+        manifest::remove_path(staging_path);
+        return Err(format!(
+            "incomplete install of {path}: {} is missing",
+            if executable.is_empty() {
+                path
+            } else {
+                executable
+            }
+        ));
+    }
+
+    manifest::remove_path(path);
+
+    fs::rename(staging_path, path).map_err(|e| {
+        manifest::remove_path(staging_path);
+        format!("failed to move {staging_path} to {path}: {e}")
+    })
+}
+
+/// Returns the path of `executable` inside the install at `path`; an empty `executable`
+/// means the install is a single executable file.
+pub fn get_executable_path(path: &str, executable: &str) -> PathBuf {
+    if executable.is_empty() {
+        PathBuf::from(path)
+    } else {
+        Path::new(path).join(executable)
+    }
+}
+
+/// Returns the absolute path of the executable of a completed install of `tool` at
+/// `path`. Only committed installs are recorded in the install manifest, together with the
+/// location of their executable, so this works the same for every kind of package.
+///
+/// A package that holds several executables, like the apphost and the `.dll` of the
+/// language server, passes the one it needs as `executable`; the install only counts when
+/// that file is present, so switching between them re-extracts the package.
+pub fn get_installed_executable(
+    tool: &str,
+    path: &str,
+    executable: Option<&str>,
+) -> Option<String> {
+    find_installed_executable(&InstallManifest::load(), tool, path, executable)
+}
+
+fn find_installed_executable(
+    manifest: &InstallManifest,
+    tool: &str,
+    path: &str,
+    executable: Option<&str>,
+) -> Option<String> {
+    let installed = manifest.find(tool, path)?;
+    let executable = get_executable_path(path, executable.unwrap_or(&installed.executable));
+
+    executable.is_file().then(|| get_absolute_path(&executable))
+}
+
+/// Returns the version of the install of `tool` whose executable is at `executable_path`,
+/// or `None` when the executable is not managed by the extension.
+pub fn get_installed_version(tool: &str, executable_path: &str) -> Option<String> {
+    InstallManifest::load()
+        .tools
+        .get(tool)?
+        .iter()
+        .find(|installed| {
+            get_absolute_path(&get_executable_path(&installed.path, &installed.executable))
+                == executable_path
+        })
+        .map(|installed| installed.version.clone())
+}
+
+/// Makes every file under `dir` executable, for unpacked archives that do not record
+/// which files are.
+pub fn make_files_executable(dir: &str) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("failed to list version directory {e}"))?;
+    let mut q = std::collections::VecDeque::from_iter(entries);
+    while !q.is_empty() {
+        let entry = q.pop_front().unwrap();
+        let entry = entry.map_err(|e| format!("failed to load directory entry {e}"))?;
+        let filetype = entry
+            .file_type()
+            .map_err(|e| format!("failed to get file type {e}"))?;
+        if filetype.is_dir() {
+            let sub_entries = fs::read_dir(entry.path())
+                .map_err(|e| format!("failed to list sub-directory {e}"))?;
+            for sub_entry in sub_entries {
+                q.push_back(sub_entry);
+            }
+        } else if filetype.is_file() {
+            let path = entry.path();
+            zed::make_file_executable(&path.to_string_lossy())
+                .map_err(|e| format!("failed to make {} executable: {e}", path.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolves `path`, relative to the extension work directory, to an absolute path.
+pub fn get_absolute_path(path: &Path) -> String {
+    std::env::current_dir()
+        .map(|current_dir| current_dir.join(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Removes leftovers of installs that were interrupted, e.g. by Zed quitting mid-download,
+/// and forgets manifest entries whose files are gone.
+pub fn cleanup_partial_installs() {
+    if let Ok(entries) = fs::read_dir(".") {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+
+            if file_name.starts_with(STAGING_PREFIX) {
+                println!("[zed-roslynls] Removing partial install {file_name}");
+                manifest::remove_path(&file_name);
+            }
+        }
+    }
+
+    let mut manifest = InstallManifest::load();
+    let mut changed = false;
+
+    for versions in manifest.tools.values_mut() {
+        let count = versions.len();
+        versions.retain(|installed| Path::new(&installed.path).exists());
+        changed |= versions.len() != count;
+    }
+
+    if changed {
+        manifest.save().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_installed_executable_follows_host_mode() {
+        let dir = std::env::temp_dir().join("zed-roslynls-install");
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(dir.join("server")).unwrap();
+        fs::write(dir.join("server/roslyn"), "").unwrap();
+
+        let path = dir.to_string_lossy();
+        let mut manifest = InstallManifest::default();
+        manifest.record("roslyn", "1.0.0", &path, "server/roslyn");
+
+        // Installed for the apphost host mode, then switched to the dotnet host mode.
+        assert_eq!(
+            find_installed_executable(&manifest, "roslyn", &path, Some("server/roslyn")),
+            Some(dir.join("server/roslyn").to_string_lossy().to_string())
+        );
+        assert_eq!(
+            find_installed_executable(&manifest, "roslyn", &path, Some("server/roslyn.dll")),
+            None
+        );
+
+        fs::write(dir.join("server/roslyn.dll"), "").unwrap();
+        assert_eq!(
+            find_installed_executable(&manifest, "roslyn", &path, Some("server/roslyn.dll")),
+            Some(dir.join("server/roslyn.dll").to_string_lossy().to_string())
+        );
+        assert_eq!(
+            find_installed_executable(&manifest, "roslyn", &path, None),
+            Some(dir.join("server/roslyn").to_string_lossy().to_string())
+        );
+        assert_eq!(
+            find_installed_executable(&manifest, "other", &path, None),
+            None
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+const MANIFEST_FILE: &str = "install-manifest.json";
+
+pub const DEFAULT_KEEP_PREVIOUS_VERSIONS: usize = 1;
+
+/// Records which tool owns which entry of the extension work directory, so that an
+/// install of one tool never removes the files of another.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InstallManifest {
+    /// Installed versions per tool, oldest first.
+    pub tools: BTreeMap<String, Vec<InstalledVersion>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledVersion {
+    pub version: String,
+    /// Path of the install, relative to the extension work directory.
+    pub path: String,
+    /// Path of the executable, relative to `path`; empty when the install is the
+    /// executable itself.
+    #[serde(default)]
+    pub executable: String,
+}
+
+impl InstallManifest {
+    pub fn load() -> Self {
+        fs::read(MANIFEST_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the manifest next to its final location first and renames it into place, so
+    /// an interrupted save leaves the previous manifest intact.
+    pub fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        let temp_file = format!("{MANIFEST_FILE}.tmp");
+
+        fs::write(&temp_file, content)
+            .and_then(|_| fs::rename(&temp_file, MANIFEST_FILE))
+            .map_err(|e| {
+                fs::remove_file(&temp_file).ok();
+                format!("failed to write {MANIFEST_FILE}: {e}")
+            })
+    }
+
+    /// Records `version` as the most recent install of `tool`.
+    pub fn record(&mut self, tool: &str, version: &str, path: &str, executable: &str) {
+        let versions = self.tools.entry(tool.to_string()).or_default();
+
+        versions.retain(|installed| installed.path != path);
+        versions.push(InstalledVersion {
+            version: version.to_string(),
+            path: path.to_string(),
+            executable: executable.to_string(),
+        });
+    }
+
+    pub fn find(&self, tool: &str, path: &str) -> Option<&InstalledVersion> {
+        self.tools
+            .get(tool)?
+            .iter()
+            .find(|installed| installed.path == path)
+    }
+
+    /// Adds work directory entries starting with `prefix` that predate the manifest as the
+    /// oldest versions of `tool`.
+    fn adopt_untracked(&mut self, tool: &str, prefix: &str) {
+        let Ok(entries) = fs::read_dir(".") else {
+            return;
+        };
+
+        let tracked: Vec<String> = self
+            .tools
+            .values()
+            .flatten()
+            .map(|installed| installed.path.clone())
+            .collect();
+
+        let mut untracked: Vec<InstalledVersion> = entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+            .filter(|name| name.starts_with(prefix) && !tracked.contains(name))
+            .map(|name| InstalledVersion {
+                version: name[prefix.len()..].to_string(),
+                path: name,
+                executable: String::new(),
+            })
+            .collect();
+
+        if untracked.is_empty() {
+            return;
+        }
+
+        let versions = self.tools.entry(tool.to_string()).or_default();
+        untracked.append(versions);
+        *versions = untracked;
+    }
+
+    /// Drops all but the newest install and `keep_previous` older ones of `tool`,
+    /// returning the dropped entries.
+    fn prune(&mut self, tool: &str, keep_previous: usize) -> Vec<InstalledVersion> {
+        let Some(versions) = self.tools.get_mut(tool) else {
+            return Vec::new();
+        };
+
+        let excess = versions.len().saturating_sub(keep_previous + 1);
+        versions.drain(..excess).collect()
+    }
+}
+
+/// Records a completed install of `tool`, with its `executable` relative to `path`, and
+/// removes versions of the same tool that are
+/// superseded beyond the `keep_previous` most recent ones. Entries of other tools are
+/// never touched.
+pub fn record_install(
+    tool: &str,
+    prefix: &str,
+    version: &str,
+    path: &str,
+    executable: &str,
+    keep_previous: usize,
+) -> Result<(), String> {
+    let mut manifest = InstallManifest::load();
+
+    manifest.adopt_untracked(tool, prefix);
+    manifest.record(tool, version, path, executable);
+
+    for superseded in manifest.prune(tool, keep_previous) {
+        println!(
+            "[zed-roslynls] Removing superseded {tool} {} at {}",
+            superseded.version, superseded.path
+        );
+        remove_path(&superseded.path);
+    }
+
+    manifest.save()
+}
+
+pub fn remove_path(path: &str) {
+    let path = Path::new(path);
+
+    if path.is_dir() {
+        fs::remove_dir_all(path).ok();
+    } else {
+        fs::remove_file(path).ok();
+    }
+}
@@ -1,4 +1,32 @@
-use zed_extension_api::{self as zed};
+//! Helpers shared by the language server, the debug adapter and the tool manager.
+//!
+//! # File access
+//!
+//! Zed runs extensions in a sandbox that limits what they can read and write:
+//!
+//! - `std::fs` only reaches the extension work directory, which is the current directory.
+//!   Installs, the install manifest, local feeds and the projects files live there.
+//! - Files of the worktree can be read one at a time with `Worktree::read_text_file`,
+//!   by a path relative to the worktree root. The worktree cannot be listed.
+//! - Nothing else on disk, like the home directory, can be read. Other installs can
+//!   only be found by running one of the commands allowed in `extension.toml`.
+//! - `zed::download_file` unpacks archives as it downloads them, but cannot unpack a
+//!   file that is already on disk.
+
+mod archive;
+pub mod install;
+pub mod manifest;
+pub mod tar;
+pub mod verify;
+pub mod zip;
+
+use zed_extension_api::{self as zed, settings::LspSettings};
+
+pub const KEEP_PREVIOUS_VERSIONS_KEY: &str = "keep_previous_versions";
+pub const RUNTIME_IDENTIFIER_KEY: &str = "runtime_identifier";
+pub const ALLOW_PATH_FALLBACK_KEY: &str = "allow_path_fallback";
+pub const GITHUB_RELEASE_URL_KEY: &str = "github_release_url";
+const DOTNET_ROOT: &str = "DOTNET_ROOT";
 
 pub fn get_executable(name: &str) -> String {
     let (platform, _) = zed::current_platform();
@@ -26,102 +54,449 @@ pub fn get_version_dir(package_id: String, version: String) -> String {
     )
 }
 
-pub fn get_runtime_identifier() -> String {
+/// Returns the runtime identifiers to look for in a feed or release, most specific first.
+///
+/// The fallbacks follow the .NET RID graph: `linux-musl-*` builds on `linux-*`, and arm64
+/// macOS and Windows can run x64 binaries under emulation.
+pub fn get_runtime_identifiers() -> Vec<String> {
     let (platform, arch) = zed::current_platform();
 
-    format!(
-        "{os}-{arch}",
-        os = match platform {
-            zed::Os::Mac => "osx",
-            zed::Os::Linux => "linux",
-            zed::Os::Windows => "win",
-        },
-        arch = match arch {
-            zed::Architecture::Aarch64 => "arm64",
-            zed::Architecture::X86 => "x86",
-            zed::Architecture::X8664 => "x64",
-        },
-    )
+    let arch = match arch {
+        zed::Architecture::Aarch64 => "arm64",
+        zed::Architecture::X86 => "x86",
+        zed::Architecture::X8664 => "x64",
+    };
+
+    let mut runtime_identifiers = match platform {
+        zed::Os::Mac => vec![format!("osx-{arch}")],
+        zed::Os::Linux if is_musl() => vec![format!("linux-musl-{arch}"), format!("linux-{arch}")],
+        zed::Os::Linux => vec![format!("linux-{arch}")],
+        zed::Os::Windows => vec![format!("win-{arch}")],
+    };
+
+    if arch == "arm64" && platform != zed::Os::Linux {
+        runtime_identifiers.push(runtime_identifiers[0].replace("arm64", "x64"));
+    }
+
+    runtime_identifiers
+}
+
+/// Reads `key` from the settings of `lsp_name`, falling back to the `lsp.roslyn` section so
+/// that one setting applies to every tool.
+pub fn get_shared_setting<T: serde::de::DeserializeOwned>(
+    lsp_name: &str,
+    worktree: &zed::Worktree,
+    key: &str,
+) -> Option<T> {
+    get_setting(lsp_name, worktree, key).or_else(|| {
+        get_setting(
+            crate::language_servers::Roslyn::LANGUAGE_SERVER_ID,
+            worktree,
+            key,
+        )
+    })
+}
+
+pub fn get_runtime_identifier_override(lsp_name: &str, worktree: &zed::Worktree) -> Option<String> {
+    get_shared_setting(lsp_name, worktree, RUNTIME_IDENTIFIER_KEY)
 }
 
-pub fn ensure_github_release<F>(
-    package: &str,
-    repo: &str,
-    tag: &str,
-    get_package_id: F,
-    download_file_type: zed::DownloadedFileType,
-) -> Result<String, String>
-where
-    F: Fn() -> String,
-{
-    let package_id = get_package_id().clone();
-    let download_path = get_version_dir(package_id.clone(), tag.into());
-
-    if std::fs::metadata(&download_path).is_ok() {
+/// Returns the runtime identifiers to try for `tool`, which publishes builds for
+/// `published`.
+///
+/// An overridden RID is used as is and must be one that `tool` publishes; otherwise the
+/// detected RIDs without a build of `tool` are skipped.
+pub fn get_tool_runtime_identifiers(
+    tool: &str,
+    lsp_name: &str,
+    worktree: &zed::Worktree,
+    published: &[&str],
+) -> Result<Vec<String>, String> {
+    if let Some(runtime_identifier) = get_runtime_identifier_override(lsp_name, worktree) {
+        if !published.contains(&runtime_identifier.as_str()) {
+            return Err(format!(
+                "{RUNTIME_IDENTIFIER_KEY} \"{runtime_identifier}\" is not available for {tool}, expected one of: {}",
+                published.join(", ")
+            ));
+        }
+
+        println!("[zed-roslynls] Using {runtime_identifier} builds of {tool}");
+
+        return Ok(vec![runtime_identifier]);
+    }
+
+    let runtime_identifiers: Vec<String> = get_runtime_identifiers()
+        .into_iter()
+        .filter(|runtime_identifier| published.contains(&runtime_identifier.as_str()))
+        .collect();
+
+    if let Some(runtime_identifier) = runtime_identifiers.first() {
+        warn_if_glibc_on_musl(tool, runtime_identifier);
+    }
+
+    Ok(runtime_identifiers)
+}
+
+/// Warns when a glibc build of `tool` is used on a musl system, where it may not start.
+pub fn warn_if_glibc_on_musl(tool: &str, runtime_identifier: &str) {
+    if runtime_identifier.starts_with("linux-")
+        && !runtime_identifier.starts_with("linux-musl-")
+        && is_musl()
+    {
         println!(
-            "[zed-roslynls] {} already downloaded at: {}",
-            package, download_path
+            "[zed-roslynls] Warning: using the glibc build {runtime_identifier} of {tool} on a musl system, where it may fail to start; install a musl build and point {tool}_path at it if it does"
         );
+    }
+}
+
+/// Whether the host uses musl rather than glibc, e.g. in Alpine containers.
+fn is_musl() -> bool {
+    static IS_MUSL: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
 
-        let executable_path = match download_file_type {
-            zed_extension_api::DownloadedFileType::Uncompressed => download_path,
-            _ => {
-                let current_dir = std::env::current_dir()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
- 
-                format!("{current_dir}/{download_path}/{package}")
+    *IS_MUSL.get_or_init(|| {
+        let has_musl_loader = std::fs::read_dir("/lib").is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+        });
+
+        // The extension may not be able to see /lib, so also ask ldd, which reports
+        // "musl libc" on musl systems.
+        has_musl_loader
+            || zed::process::Command::new("ldd")
+                .arg("--version")
+                .output()
+                .is_ok_and(|output| {
+                    String::from_utf8_lossy(&output.stdout).contains("musl")
+                        || String::from_utf8_lossy(&output.stderr).contains("musl")
+                })
+    })
+}
+
+/// Resolves a local feed, a path or `file://` URL, against the extension's work directory.
+///
+/// Feeds elsewhere cannot be read (see [file access](crate::utils#file-access)), so they
+/// are rejected up front rather than failing with a permission error.
+pub fn get_local_feed_path(feed: &str) -> Result<std::path::PathBuf, String> {
+    let path = match feed.strip_prefix("file://") {
+        // file:///C:/feed on Windows has no root beyond the drive letter.
+        Some(path) => path
+            .strip_prefix('/')
+            .filter(|rest| rest.chars().nth(1) == Some(':'))
+            .unwrap_or(path),
+        None => feed,
+    };
+
+    let work_dir = std::env::current_dir()
+        .map_err(|e| format!("failed to get the extension work directory: {e}"))?;
+
+    resolve_in_work_dir(&work_dir, std::path::Path::new(path))
+}
+
+/// Joins `path` onto `work_dir` and checks that the result stays inside it. `..` is
+/// resolved first, so `feed/../../elsewhere` cannot slip past the prefix check.
+fn resolve_in_work_dir(
+    work_dir: &std::path::Path,
+    path: &std::path::Path,
+) -> Result<std::path::PathBuf, String> {
+    let mut resolved = std::path::PathBuf::new();
+
+    for component in work_dir.join(path).components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                resolved.pop();
             }
+            component => resolved.push(component),
+        }
+    }
+
+    if !resolved.starts_with(work_dir) {
+        return Err(format!(
+            "local feed {} is outside of the extension work directory {}, the only folder \
+             extensions can read; move or copy the packages into it",
+            resolved.display(),
+            work_dir.display()
+        ));
+    }
+
+    Ok(resolved)
+}
+
+/// Finds `package_id` in a local feed, at `pinned_version` if given and at the highest version
+/// otherwise.
+pub fn find_local_nuget_package(
+    feed: &str,
+    package_id: &str,
+    pinned_version: Option<&str>,
+) -> Result<(String, std::path::PathBuf), String> {
+    let feed_path = get_local_feed_path(feed)?;
+    let prefix = format!("{}.", package_id.to_lowercase());
+
+    let entries = std::fs::read_dir(&feed_path)
+        .map_err(|e| format!("failed to list local feed {}: {e}", feed_path.display()))?;
+
+    let mut latest: Option<(semver::Version, String, std::path::PathBuf)> = None;
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let lower_file_name = file_name.to_lowercase();
+
+        let Some(found_version) = lower_file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".nupkg"))
+        else {
+            continue;
         };
 
-        return Ok(executable_path);
+        // Take the version from the original file name to keep its casing.
+        let version = &file_name[prefix.len()..prefix.len() + found_version.len()];
+
+        if pinned_version
+            .is_some_and(|pinned_version| !pinned_version.eq_ignore_ascii_case(version))
+        {
+            continue;
+        }
+
+        let Ok(parsed) = semver::Version::parse(version) else {
+            println!("[zed-roslynls] Skipping {file_name}: unrecognized version {version}");
+            continue;
+        };
+
+        if latest
+            .as_ref()
+            .is_none_or(|(current, _, _)| parsed > *current)
+        {
+            latest = Some((parsed, version.to_string(), entry.path()));
+        }
     }
 
-    match zed::github_release_by_tag_name(repo, tag) {
-        Ok(release) => {
-            let asset = release.assets.iter().find(|asset| asset.name == package_id);
-
-            println!("[zed-roslynls] Found asset: {:?} {:?}", package_id, asset);
-
-            if let Some(asset) = asset {
-                let download_url = &asset.download_url;
-                println!(
-                    "[zed-roslynls] Downloading from: {}, to: {}",
-                    download_url, download_path
-                );
-
-                zed::download_file(download_url, download_path.as_str(), download_file_type)
-                    .map_err(|e| format!("Failed to download {package}: {e}"))?;
-
-                match download_file_type {
-                    zed_extension_api::DownloadedFileType::Uncompressed => {
-                        zed::make_file_executable(download_path.as_str())
-                            .expect(format!("Failed to make {package} executable").as_str());
-
-                        Ok(download_path)
-                    }
-                    zed_extension_api::DownloadedFileType::GzipTar => {
-                        let executable_path = format!("{download_path}/{package}");
-                        zed::make_file_executable(executable_path.as_str())
-                            .expect(format!("Failed to make {package} executable").as_str());
-                        Ok(executable_path)
-                    }
-                    zed_extension_api::DownloadedFileType::Zip => Err("Not implemented".into()),
-                    zed_extension_api::DownloadedFileType::Gzip => Err("Not implemented".into()),
-                }
-            } else {
-                println!(
-                    "[zed-roslynls] No suitable {package} asset found for the current platform"
-                );
-                Ok(package.into())
-            }
+    latest
+        .map(|(_, version, path)| (version, path))
+        .ok_or_else(|| {
+            format!(
+                "no {package_id}{} package found in local feed {}",
+                pinned_version
+                    .map(|version| format!(" {version}"))
+                    .unwrap_or_default(),
+                feed_path.display()
+            )
+        })
+}
+
+pub fn http_get(url: &str) -> Result<Vec<u8>, String> {
+    let request = zed::http_client::HttpRequest::builder()
+        .method(zed::http_client::HttpMethod::Get)
+        .url(url)
+        .build()?;
+
+    let response = zed::http_client::fetch(&request)?;
+
+    Ok(response.body)
+}
+
+/// Parses a package version or a release tag such as `v0.0.2`, with SemVer ordering of
+/// prerelease versions like `5.3.0-1.25510.11`.
+pub fn parse_version(version: &str) -> Option<semver::Version> {
+    semver::Version::parse(version.trim_start_matches('v')).ok()
+}
+
+/// Orders versions by SemVer, placing versions that are not SemVer first.
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    parse_version(a).cmp(&parse_version(b))
+}
+
+/// Reads `key` from the `settings` of the `lsp.<lsp_name>` section.
+pub fn get_setting<T: serde::de::DeserializeOwned>(
+    lsp_name: &str,
+    worktree: &zed::Worktree,
+    key: &str,
+) -> Option<T> {
+    LspSettings::for_worktree(lsp_name, worktree)
+        .ok()
+        .and_then(|lsp_settings| lsp_settings.settings)
+        .and_then(|settings| serde_json::from_value(settings.get(key)?.clone()).ok())
+}
+
+pub fn get_keep_previous_versions(lsp_name: &str, worktree: &zed::Worktree) -> usize {
+    get_setting(lsp_name, worktree, KEEP_PREVIOUS_VERSIONS_KEY)
+        .unwrap_or(manifest::DEFAULT_KEEP_PREVIOUS_VERSIONS)
+}
+
+pub fn set_installation_status(
+    language_server_id: Option<&zed::LanguageServerId>,
+    status: zed::LanguageServerInstallationStatus,
+) {
+    if let Some(language_server_id) = language_server_id {
+        zed::set_language_server_installation_status(language_server_id, &status);
+    }
+}
+
+/// Locates the `dotnet` host, preferring `DOTNET_ROOT` over the worktree's `PATH`.
+pub fn find_dotnet(worktree: &zed::Worktree) -> Option<String> {
+    let dotnet = get_executable("dotnet");
+
+    worktree
+        .shell_env()
+        .into_iter()
+        .find(|(key, value)| key == DOTNET_ROOT && !value.is_empty())
+        .map(|(_, root)| {
+            std::path::Path::new(&root)
+                .join(&dotnet)
+                .to_string_lossy()
+                .to_string()
+        })
+        .or_else(|| worktree.which(&dotnet))
+}
+
+/// Returns the version and folder of each SDK of the `dotnet` on the worktree's `PATH`, or
+/// `None` when it could not be run.
+pub fn list_dotnet_sdks(worktree: &zed::Worktree) -> Option<Vec<(String, std::path::PathBuf)>> {
+    // `dotnet --list-sdks` prints `<version> [<sdks folder>]` per SDK.
+    zed::process::Command::new("dotnet")
+        .arg("--list-sdks")
+        .envs(worktree.shell_env())
+        .output()
+        .ok()
+        .filter(|output| output.status == Some(0))
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| {
+                    let (version, folder) = line.trim().split_once(' ')?;
+                    let folder = folder.trim().strip_prefix('[')?.strip_suffix(']')?;
+                    Some((
+                        version.to_string(),
+                        std::path::Path::new(folder).join(version),
+                    ))
+                })
+                .collect()
+        })
+}
+
+/// Returns the environment of a tool's process: the worktree's shell environment, with
+/// `DOTNET_ROOT` set to `dotnet_root`, a managed SDK that also goes first on `PATH`, or
+/// otherwise to the install of the `dotnet` on `PATH` when it is not set, and the
+/// `binary.env` setting on top.
+///
+/// `installed_sdks` is what [`list_dotnet_sdks`] returned; callers list the SDKs once and
+/// share the result, since running `dotnet` is slow.
+pub fn get_command_env(
+    lsp_name: &str,
+    worktree: &zed::Worktree,
+    dotnet_root: Option<&str>,
+    installed_sdks: Option<&[(String, std::path::PathBuf)]>,
+) -> zed::EnvVars {
+    let mut env: std::collections::BTreeMap<String, String> =
+        worktree.shell_env().into_iter().collect();
+
+    if let Some(dotnet_root) = dotnet_root {
+        let separator = match zed::current_platform().0 {
+            zed::Os::Windows => ';',
+            _ => ':',
+        };
+        let path = match env.get("PATH") {
+            Some(path) => format!("{dotnet_root}{separator}{path}"),
+            None => dotnet_root.to_string(),
+        };
+
+        env.insert("PATH".into(), path);
+        env.insert(DOTNET_ROOT.into(), dotnet_root.into());
+    } else if !env.contains_key(DOTNET_ROOT) {
+        // The `dotnet` on PATH is often a symlink, so its install is taken from the SDK
+        // folders it reports.
+        let dotnet_root = installed_sdks
+            .into_iter()
+            .flatten()
+            .find_map(|(_, sdk)| Some(sdk.parent()?.parent()?.to_path_buf()));
+
+        if let Some(dotnet_root) = dotnet_root {
+            println!(
+                "[zed-roslynls] Setting {DOTNET_ROOT} for {lsp_name} to {}",
+                dotnet_root.display()
+            );
+            env.insert(
+                DOTNET_ROOT.into(),
+                dotnet_root.to_string_lossy().to_string(),
+            );
         }
-        Err(e) => {
-            println!("[zed-roslynls] Failed to fetch {package} release info: {e}");
+    }
 
-            Ok(package.to_string())
+    if let Some(user_env) = LspSettings::for_worktree(lsp_name, worktree)
+        .ok()
+        .and_then(|lsp_settings| lsp_settings.binary)
+        .and_then(|binary_settings| binary_settings.env)
+    {
+        env.extend(user_env);
+    }
+
+    env.into_iter().collect()
+}
+
+/// Searches `root` breadth-first for a file called `file_name`.
+pub fn find_file(root: &std::path::Path, file_name: &str) -> Option<std::path::PathBuf> {
+    let mut queue = std::collections::VecDeque::from([root.to_path_buf()]);
+
+    while let Some(dir) = queue.pop_front() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                queue.push_back(path);
+            } else if entry.file_name() == file_name {
+                return Some(path);
+            }
         }
     }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_version_order() {
+        use std::cmp::Ordering::*;
+
+        assert_eq!(compare_versions("1.0.0-rc.1", "1.0.0"), Less);
+        // Numeric prerelease parts compare as numbers, others lexically.
+        assert_eq!(compare_versions("1.0.0-rc.10", "1.0.0-rc.2"), Greater);
+        assert_eq!(
+            compare_versions("5.3.0-1.25510.11", "5.3.0-1.9999.1"),
+            Greater
+        );
+        assert_eq!(compare_versions("1.0.0-alpha", "1.0.0-beta"), Less);
+        assert_eq!(compare_versions("1.0.0-1", "1.0.0-alpha"), Less);
+        assert_eq!(compare_versions("0.10.0", "0.9.0"), Greater);
+        // Release tags may start with a `v`.
+        assert_eq!(compare_versions("v0.0.3", "0.0.2"), Greater);
+        assert_eq!(compare_versions("v1.2.3", "1.2.3"), Equal);
+        // Versions that are not SemVer sort first.
+        assert_eq!(compare_versions("nightly", "0.0.1"), Less);
+        assert_eq!(parse_version("v1.2"), None);
+    }
+
+    #[test]
+    fn test_local_feed_stays_in_work_dir() {
+        let work_dir = Path::new("/work");
+
+        assert_eq!(
+            resolve_in_work_dir(work_dir, Path::new("feed/./packages")),
+            Ok(work_dir.join("feed/packages"))
+        );
+        assert_eq!(
+            resolve_in_work_dir(work_dir, Path::new("feed/../other")),
+            Ok(work_dir.join("other"))
+        );
+        assert!(resolve_in_work_dir(work_dir, Path::new("../elsewhere")).is_err());
+        assert!(resolve_in_work_dir(work_dir, Path::new("feed/../../elsewhere")).is_err());
+        assert!(resolve_in_work_dir(work_dir, Path::new("/elsewhere")).is_err());
+    }
 }
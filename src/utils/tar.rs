@@ -0,0 +1,162 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use super::archive;
+
+/// Extracts a gzip compressed tar archive into `destination`. Symbolic and hard links are
+/// extracted as copies of their targets.
+///
+/// Like [`super::zip::extract`], this is for archives that are verified before they are
+/// unpacked, which `zed::download_file` cannot do.
+pub fn extract(archive_path: &Path, destination: &Path) -> Result<(), String> {
+    let file = File::open(archive_path)
+        .map_err(|e| format!("failed to open {}: {e}", archive_path.display()))?;
+    let mut tar = ::tar::Archive::new(GzDecoder::new(io::BufReader::new(file)));
+    let mut links = Vec::new();
+
+    for entry in tar
+        .entries()
+        .map_err(|e| format!("invalid tar archive: {e}"))?
+    {
+        let mut entry = entry.map_err(|e| format!("invalid tar archive: {e}"))?;
+        let path = archive::get_entry_path(
+            &entry
+                .path()
+                .map_err(|e| format!("invalid tar archive: {e}"))?,
+        )?;
+        let entry_type = entry.header().entry_type();
+        let target = destination.join(&path);
+
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+
+        if entry_type.is_dir() {
+            fs::create_dir_all(&target)
+                .map_err(|e| format!("failed to create {}: {e}", target.display()))?;
+        } else if entry_type.is_symlink() || entry_type.is_hard_link() {
+            let link_target = entry
+                .link_name()
+                .map_err(|e| format!("invalid tar archive: {e}"))?
+                .ok_or_else(|| {
+                    format!("invalid tar archive: link {} has no target", path.display())
+                })?;
+            links.push(archive::resolve_link(
+                &path,
+                &link_target,
+                entry_type.is_symlink(),
+            )?);
+        } else if entry_type.is_file() {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+            }
+
+            let mut output = File::create(&target)
+                .map_err(|e| format!("failed to create {}: {e}", target.display()))?;
+            io::copy(&mut entry, &mut output)
+                .map_err(|e| format!("failed to extract {}: {e}", path.display()))?;
+        }
+    }
+
+    archive::copy_links(destination, links)
+}
+
+#[cfg(test)]
+mod tests {
+    use ::tar::{Builder, EntryType, Header};
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+
+    /// Builds a header by hand, since the tar crate refuses to write unsafe names.
+    fn header(name: &str, entry_type: EntryType, size: u64, link: Option<&str>) -> Header {
+        let mut header = Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        if let Some(link) = link {
+            header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+        }
+        header.set_entry_type(entry_type);
+        header.set_size(size);
+        header.set_mode(0o755);
+        header.set_cksum();
+        header
+    }
+
+    fn extract_entries(
+        name: &str,
+        entries: &[(&str, EntryType, &[u8], Option<&str>)],
+    ) -> Result<std::path::PathBuf, String> {
+        let dir = std::env::temp_dir().join(format!("zed-roslynls-tar-{name}"));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        let archive_path = dir.join("archive.tar.gz");
+        let mut builder = Builder::new(GzEncoder::new(
+            File::create(&archive_path).unwrap(),
+            Compression::fast(),
+        ));
+        for (name, entry_type, data, link) in entries {
+            builder
+                .append(&header(name, *entry_type, data.len() as u64, *link), *data)
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let destination = dir.join("out");
+        extract(&archive_path, &destination).map(|_| destination)
+    }
+
+    #[test]
+    fn test_extract_files_and_links() {
+        let destination = extract_entries(
+            "links",
+            &[
+                ("./sdk/", EntryType::Directory, b"", None),
+                ("./sdk/dotnet", EntryType::Regular, b"host", None),
+                (
+                    "./bin/dotnet",
+                    EntryType::Symlink,
+                    b"",
+                    Some("../sdk/dotnet"),
+                ),
+                ("./dotnet", EntryType::Link, b"", Some("sdk/dotnet")),
+                ("./latest", EntryType::Symlink, b"", Some("sdk")),
+            ],
+        )
+        .unwrap();
+
+        for path in ["sdk/dotnet", "bin/dotnet", "dotnet", "latest/dotnet"] {
+            assert_eq!(fs::read(destination.join(path)).unwrap(), b"host", "{path}");
+        }
+    }
+
+    #[test]
+    fn test_refuse_traversal() {
+        assert!(
+            extract_entries(
+                "traversal",
+                &[("../evil", EntryType::Regular, b"evil", None)]
+            )
+            .is_err()
+        );
+        assert!(
+            extract_entries(
+                "link-traversal",
+                &[("passwd", EntryType::Symlink, b"", Some("/etc/passwd"))]
+            )
+            .is_err()
+        );
+        assert!(
+            extract_entries(
+                "missing-link",
+                &[("dotnet", EntryType::Symlink, b"", Some("sdk/dotnet"))]
+            )
+            .is_err()
+        );
+    }
+}
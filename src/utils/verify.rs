@@ -0,0 +1,145 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use sha2::{Digest, Sha512};
+
+const SIGNATURE_FILE: &str = ".signature.p7s";
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Checks the SHA-512 hash of the file at `path` against `expected_hash`, which may be
+/// base64 (as published by NuGet feeds) or hex encoded.
+pub fn verify_sha512(path: &Path, expected_hash: &str) -> Result<(), String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("failed to open {}: {e}", path.display()))?;
+    let mut hasher = Sha512::new();
+    io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+
+    let digest = hasher.finalize();
+    let expected_hash = expected_hash.trim();
+
+    let matches =
+        if expected_hash.len() == 128 && expected_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            to_hex(&digest).eq_ignore_ascii_case(expected_hash)
+        } else {
+            BASE64.encode(digest) == expected_hash
+        };
+
+    if matches {
+        Ok(())
+    } else {
+        Err(format!(
+            "SHA-512 mismatch: expected {expected_hash}, got {}",
+            BASE64.encode(digest)
+        ))
+    }
+}
+
+/// Checks the metadata of an unpacked NuGet package: the `.nuspec` must describe the
+/// expected package and version, and the package must carry a signature manifest when
+/// `require_signature` is set.
+///
+/// The extension has no access to a certificate store, so only the presence and PKCS#7
+/// framing of `.signature.p7s` are checked, not the certificate chain; the SHA-512 check
+/// is what guards the content.
+pub fn verify_nuget_package(
+    dir: &Path,
+    package_id: &str,
+    version: &str,
+    require_signature: bool,
+) -> Result<(), String> {
+    let nuspec_path = fs::read_dir(dir)
+        .map_err(|e| format!("failed to list {}: {e}", dir.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "nuspec"))
+        .ok_or("package has no .nuspec manifest")?;
+
+    let nuspec = fs::read_to_string(&nuspec_path)
+        .map_err(|e| format!("failed to read {}: {e}", nuspec_path.display()))?;
+
+    let nuspec_id = get_xml_element(&nuspec, "id").unwrap_or_default();
+    if !nuspec_id.eq_ignore_ascii_case(package_id) {
+        return Err(format!("manifest describes package {nuspec_id}"));
+    }
+
+    let nuspec_version = get_xml_element(&nuspec, "version").unwrap_or_default();
+    if !nuspec_version.eq_ignore_ascii_case(version) {
+        return Err(format!("manifest describes version {nuspec_version}"));
+    }
+
+    match fs::read(dir.join(SIGNATURE_FILE)) {
+        // A PKCS#7 signature is a DER encoded SEQUENCE.
+        Ok(signature) if signature.first() != Some(&0x30) => {
+            Err("package signature manifest is malformed".into())
+        }
+        Ok(_) => Ok(()),
+        Err(_) if require_signature => Err(format!("package has no {SIGNATURE_FILE}")),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Returns the text of the first `<name>` element in `xml`, ignoring namespace prefixes
+/// and attributes.
+pub fn get_xml_element(xml: &str, name: &str) -> Option<String> {
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let tag_end = rest.find('>')?;
+        let tag = &rest[..tag_end];
+        let tag_name = tag.split_whitespace().next().unwrap_or_default();
+        let local_name = tag_name.rsplit(':').next().unwrap_or_default();
+
+        if local_name == name && !tag.ends_with('/') {
+            let content = &rest[tag_end + 1..];
+            let content_end = content.find("</")?;
+            return Some(content[..content_end].trim().to_string());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unpack(name: &str, signature: Option<&[u8]>) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("zed-roslynls-verify-{name}"));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("package.nuspec"),
+            "<package><metadata><id>Package</id><version>1.0.0</version></metadata></package>",
+        )
+        .unwrap();
+
+        if let Some(signature) = signature {
+            fs::write(dir.join(SIGNATURE_FILE), signature).unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn test_nuget_package_signature() {
+        let signed = unpack("signed", Some(&[0x30, 0x82]));
+        assert!(verify_nuget_package(&signed, "package", "1.0.0", true).is_ok());
+        assert!(verify_nuget_package(&signed, "other", "1.0.0", true).is_err());
+        assert!(verify_nuget_package(&signed, "package", "2.0.0", true).is_err());
+
+        let unsigned = unpack("unsigned", None);
+        assert!(verify_nuget_package(&unsigned, "package", "1.0.0", true).is_err());
+        assert!(verify_nuget_package(&unsigned, "package", "1.0.0", false).is_ok());
+
+        let malformed = unpack("malformed", Some(b"not a signature"));
+        assert!(verify_nuget_package(&malformed, "package", "1.0.0", false).is_err());
+    }
+}
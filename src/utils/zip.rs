@@ -0,0 +1,136 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use super::archive;
+
+/// Extracts a zip archive (such as a `.nupkg`) into `destination`, streaming each entry
+/// from disk. ZIP64 archives are supported and links are extracted as copies.
+///
+/// Used for packages that are already on disk, which `zed::download_file` cannot unpack
+/// (see [file access](crate::utils#file-access)).
+pub fn extract(archive_path: &Path, destination: &Path) -> Result<(), String> {
+    let file = File::open(archive_path)
+        .map_err(|e| format!("failed to open {}: {e}", archive_path.display()))?;
+    let mut zip = ::zip::ZipArchive::new(io::BufReader::new(file))
+        .map_err(|e| format!("invalid zip archive {}: {e}", archive_path.display()))?;
+    let mut links = Vec::new();
+
+    for index in 0..zip.len() {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|e| format!("invalid zip archive: {e}"))?;
+        let path = archive::get_entry_path(Path::new(entry.name()))?;
+        let target = destination.join(&path);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&target)
+                .map_err(|e| format!("failed to create {}: {e}", target.display()))?;
+            continue;
+        }
+
+        if entry.is_symlink() {
+            let mut link_target = String::new();
+            entry
+                .read_to_string(&mut link_target)
+                .map_err(|e| format!("failed to read link {}: {e}", path.display()))?;
+            links.push(archive::resolve_link(&path, Path::new(&link_target), true)?);
+            continue;
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+        }
+
+        let mut output = File::create(&target)
+            .map_err(|e| format!("failed to create {}: {e}", target.display()))?;
+        io::copy(&mut entry, &mut output)
+            .map_err(|e| format!("failed to extract {}: {e}", path.display()))?;
+    }
+
+    archive::copy_links(destination, links)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use ::zip::ZipWriter;
+    use ::zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    fn extract_entries(
+        name: &str,
+        write: impl FnOnce(&mut ZipWriter<File>),
+    ) -> Result<std::path::PathBuf, String> {
+        let dir = std::env::temp_dir().join(format!("zed-roslynls-zip-{name}"));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        let archive_path = dir.join("archive.zip");
+        let mut writer = ZipWriter::new(File::create(&archive_path).unwrap());
+        write(&mut writer);
+        writer.finish().unwrap();
+
+        let destination = dir.join("out");
+        extract(&archive_path, &destination).map(|_| destination)
+    }
+
+    #[test]
+    fn test_extract_files_and_links() {
+        let destination = extract_entries("links", |writer| {
+            let options = SimpleFileOptions::default();
+            writer.add_directory("content/", options).unwrap();
+            writer.start_file("content/server.dll", options).unwrap();
+            writer.write_all(b"server").unwrap();
+            writer
+                .add_symlink("content/current.dll", "server.dll", options)
+                .unwrap();
+        })
+        .unwrap();
+
+        assert_eq!(
+            fs::read(destination.join("content/server.dll")).unwrap(),
+            b"server"
+        );
+        assert_eq!(
+            fs::read(destination.join("content/current.dll")).unwrap(),
+            b"server"
+        );
+    }
+
+    #[test]
+    fn test_refuse_traversal() {
+        let result = extract_entries("traversal", |writer| {
+            writer
+                .start_file("../evil", SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(b"evil").unwrap();
+        });
+        assert!(result.is_err());
+
+        let result = extract_entries("link-traversal", |writer| {
+            writer
+                .add_symlink("passwd", "../../etc/passwd", SimpleFileOptions::default())
+                .unwrap();
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_extract_zip64() {
+        // Entries of more than 4 GiB need ZIP64 headers; large_file writes them for a
+        // small entry too.
+        let destination = extract_entries("zip64", |writer| {
+            writer
+                .start_file("large.bin", SimpleFileOptions::default().large_file(true))
+                .unwrap();
+            writer.write_all(b"large").unwrap();
+        })
+        .unwrap();
+
+        assert_eq!(fs::read(destination.join("large.bin")).unwrap(), b"large");
+    }
+}
@@ -22,27 +22,41 @@ public sealed class MessageProcessor
     private readonly string solution;
     private readonly string[] projects;
     private readonly string lsp;
+    private readonly string? lspDll;
     private readonly ILspLogger lspLogger;
     private readonly RpcType wrapperRpcType;
     private readonly RpcType lspRpcType;
 
-    private MessageProcessor(string projectRoot, string solution, string[] projects, RpcType wrapperRpcType, string lsp, RpcType lspRpcType, ILspLogger lspLogger)
+    private MessageProcessor(string projectRoot, string solution, string[] projects, RpcType wrapperRpcType, string lsp, string? lspDll, RpcType lspRpcType, ILspLogger lspLogger)
     {
         this.projectRoot = projectRoot;
         this.solution = solution;
         this.projects = projects;
         this.lsp = lsp;
+        this.lspDll = lspDll;
         this.lspLogger = lspLogger;
         this.wrapperRpcType = wrapperRpcType;
         this.lspRpcType = lspRpcType;
     }
 
-    public static MessageProcessor Create(string projectRoot, RpcType wrapperRpcType, string lsp, RpcType lspRpcType, ILspLogger lspLogger)
+    public static MessageProcessor Create(string projectRoot, string? solutionPath, string? projectsFile, RpcType wrapperRpcType, string lsp, string? lspDll, RpcType lspRpcType, ILspLogger lspLogger)
     {
         var solutions = Array.Empty<string>();
         var projects = Array.Empty<string>();
 
-        if (!string.IsNullOrWhiteSpace(projectRoot) && Directory.Exists(projectRoot))
+        if (!string.IsNullOrWhiteSpace(projectsFile))
+        {
+            // A file with a JSON array of the projects picked by the extension, instead of
+            // the whole solution. A large subset does not fit on the command line.
+            projects = JsonSerializer.Deserialize(File.ReadAllText(projectsFile), LspJsonSerializerContext.Default.StringArray) ?? [];
+        }
+        else if (!string.IsNullOrWhiteSpace(solutionPath))
+        {
+            // The solution, or solution filter, chosen by the extension already lists the
+            // projects to load.
+            solutions = [Path.GetFullPath(solutionPath, projectRoot)];
+        }
+        else if (!string.IsNullOrWhiteSpace(projectRoot) && Directory.Exists(projectRoot))
         {
             solutions = Directory.GetFiles(projectRoot, "*.slnx", SearchOption.TopDirectoryOnly);
 
@@ -64,7 +78,7 @@ public sealed class MessageProcessor
 
         var projectUris = projects.Select(p => new Uri(p).AbsoluteUri).ToArray();
 
-        return new(projectRoot, solutionUri, projectUris, wrapperRpcType, lsp, lspRpcType, lspLogger);
+        return new(projectRoot, solutionUri, projectUris, wrapperRpcType, lsp, lspDll, lspRpcType, lspLogger);
     }
 
     public async Task ProcessAsync(CancellationToken cancellationToken)
@@ -77,6 +91,12 @@ public sealed class MessageProcessor
         process.StartInfo.RedirectStandardOutput = true;
         process.StartInfo.RedirectStandardError = true;
         process.StartInfo.CreateNoWindow = true;
+
+        if (!string.IsNullOrEmpty(lspDll))
+        {
+            process.StartInfo.ArgumentList.Add(lspDll);
+        }
+
         process.StartInfo.ArgumentList.Add("--logLevel");
         process.StartInfo.ArgumentList.Add("Information");
         process.StartInfo.ArgumentList.Add("--extensionLogDirectory");
@@ -164,11 +184,17 @@ public sealed class MessageProcessor
                 {
                     initialized = true;
 
-                    var solutionNotification = new OpenSolutionNotifiation(this.solution);
-                    await SendNotificationAsync(writer, solutionNotification, LspJsonSerializerContext.Default.OpenSolutionNotifiation, cancellationToken);
-
-                    var projectNotification = new OpenProjectNotification(this.projects);
-                    await SendNotificationAsync(writer, projectNotification, LspJsonSerializerContext.Default.OpenProjectNotification, cancellationToken);
+                    if (!string.IsNullOrEmpty(this.solution))
+                    {
+                        var solutionNotification = new OpenSolutionNotifiation(this.solution);
+                        await SendNotificationAsync(writer, solutionNotification, LspJsonSerializerContext.Default.OpenSolutionNotifiation, cancellationToken);
+                    }
+
+                    if (this.projects.Length > 0)
+                    {
+                        var projectNotification = new OpenProjectNotification(this.projects);
+                        await SendNotificationAsync(writer, projectNotification, LspJsonSerializerContext.Default.OpenProjectNotification, cancellationToken);
+                    }
 
                     await writer.FlushAsync(cancellationToken);
                 }
@@ -7,13 +7,16 @@ using System.Threading.Tasks;
 using ZedRoslynLS;
 
 await ConsoleApp.RunAsync(args,
-    static async (string lsp, string projectRoot, string? logFilePath = null, RpcType wrapperRpcType = RpcType.Stdio, RpcType lspRpcType = RpcType.NamedPipe, CancellationToken cancellationToken = default) =>
+    static async (string lsp, string projectRoot, string? lspDll = null, string? solution = null, string? projectsFile = null, bool discoverProjectRoot = false, string? logFilePath = null, RpcType wrapperRpcType = RpcType.Stdio, RpcType lspRpcType = RpcType.NamedPipe, CancellationToken cancellationToken = default) =>
     {
         var cts = CancellationTokenSource.CreateLinkedTokenSource(cancellationToken);
 
-        if (Environment.OSVersion.Platform == PlatformID.Unix && !string.IsNullOrEmpty(lsp))
+        // With --lsp-dll, --lsp is a dotnet host and the server lives next to the dll.
+        var serverPath = string.IsNullOrEmpty(lspDll) ? lsp : lspDll;
+
+        if (Environment.OSVersion.Platform == PlatformID.Unix && !string.IsNullOrEmpty(serverPath))
         {
-            var lspRoot = Directory.GetParent(lsp)!.FullName;
+            var lspRoot = Directory.GetParent(serverPath)!.FullName;
             var psi = new ProcessStartInfo
             {
                 FileName = "chmod",
@@ -31,11 +34,18 @@ await ConsoleApp.RunAsync(args,
             process.WaitForExit();
         }
 
+        if (discoverProjectRoot)
+        {
+            var discoveredRoot = ProjectRootFinder.Find(projectRoot);
+            Console.Error.WriteLine($"Using project root \"{discoveredRoot}\" found from \"{projectRoot}\"");
+            projectRoot = discoveredRoot;
+        }
+
         ILspLogger logger = string.IsNullOrEmpty(logFilePath)
             ? new LspNoopLogger()
             : new LspFileLogger(logFilePath);
 
-        var processor = MessageProcessor.Create(projectRoot, wrapperRpcType, lsp, lspRpcType, logger);
+        var processor = MessageProcessor.Create(projectRoot, solution, projectsFile, wrapperRpcType, lsp, lspDll, lspRpcType, logger);
 
         _ = Task.Factory.StartNew(async () =>
         {
@@ -0,0 +1,120 @@
+namespace ZedRoslynLS;
+
+using System;
+using System.Collections.Generic;
+using System.IO;
+using System.Linq;
+
+/// <summary>
+/// Finds the project root when the folder opened in Zed is not one: a subfolder of a
+/// repository, or a folder holding several of them.
+/// </summary>
+public static class ProjectRootFinder
+{
+    private const int MaxSearchDepth = 3;
+
+    private static readonly string[] SkippedDirectories = ["bin", "obj", "node_modules", "packages", "artifacts"];
+
+    /// <summary>
+    /// Returns the nearest folder with a solution, <c>Directory.Build.props</c> or
+    /// <c>global.json</c>: <paramref name="start"/> itself, then its parents up to the
+    /// repository root but below the home folder, then its subfolders, shallowest first. Falls back to
+    /// <paramref name="start"/>.
+    /// </summary>
+    public static string Find(string start)
+    {
+        if (!Directory.Exists(start))
+        {
+            return start;
+        }
+
+        // Markers above the repository, like a global.json in the home folder, do not
+        // belong to it. Outside of a repository there is no such boundary, so only the
+        // start folder itself is checked, and the walk never reaches the home folder.
+        var repositoryRoot = FindRepositoryRoot(start);
+        var home = Environment.GetFolderPath(Environment.SpecialFolder.UserProfile);
+
+        for (var directory = new DirectoryInfo(start); directory is not null; directory = directory.Parent)
+        {
+            if (!IsSamePath(directory.FullName, start) && IsSamePath(directory.FullName, home))
+            {
+                break;
+            }
+
+            if (IsProjectRoot(directory.FullName))
+            {
+                return directory.FullName;
+            }
+
+            if (repositoryRoot is null || IsSamePath(directory.FullName, repositoryRoot))
+            {
+                break;
+            }
+        }
+
+        var level = new List<string> { start };
+
+        for (var depth = 0; depth < MaxSearchDepth && level.Count > 0; depth++)
+        {
+            level = level
+                .SelectMany(GetSubdirectories)
+                .Order(StringComparer.Ordinal)
+                .ToList();
+
+            var root = level.FirstOrDefault(IsProjectRoot);
+
+            if (root is not null)
+            {
+                return root;
+            }
+        }
+
+        return start;
+    }
+
+    private static string? FindRepositoryRoot(string start)
+    {
+        for (var directory = new DirectoryInfo(start); directory is not null; directory = directory.Parent)
+        {
+            if (Directory.Exists(Path.Join(directory.FullName, ".git")) || File.Exists(Path.Join(directory.FullName, ".git")))
+            {
+                return directory.FullName;
+            }
+        }
+
+        return null;
+    }
+
+    private static bool IsSamePath(string left, string right)
+    {
+        var comparison = OperatingSystem.IsWindows() ? StringComparison.OrdinalIgnoreCase : StringComparison.Ordinal;
+
+        return string.Equals(Path.TrimEndingDirectorySeparator(left), Path.TrimEndingDirectorySeparator(right), comparison);
+    }
+
+    private static bool IsProjectRoot(string directory)
+    {
+        return Directory.EnumerateFiles(directory, "*.slnx").Any()
+            || Directory.EnumerateFiles(directory, "*.sln").Any()
+            || File.Exists(Path.Join(directory, "Directory.Build.props"))
+            || File.Exists(Path.Join(directory, "global.json"));
+    }
+
+    private static IEnumerable<string> GetSubdirectories(string directory)
+    {
+        try
+        {
+            return Directory.EnumerateDirectories(directory)
+                .Where(subdirectory =>
+                {
+                    var name = Path.GetFileName(subdirectory);
+                    return !name.StartsWith('.') && !SkippedDirectories.Contains(name, StringComparer.OrdinalIgnoreCase);
+                })
+                .ToList();
+        }
+        catch (Exception e) when (e is UnauthorizedAccessException or IOException)
+        {
+            return [];
+        }
+    }
+}
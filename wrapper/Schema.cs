@@ -94,6 +94,7 @@ public sealed class Position
 [JsonSerializable(typeof(OpenSolutionNotifiation))]
 [JsonSerializable(typeof(OpenProjectNotification))]
 [JsonSerializable(typeof(TextDocumentDiagnosticRequest))]
+[JsonSerializable(typeof(string[]))]
 public partial class LspJsonSerializerContext : JsonSerializerContext
 {
 }